@@ -1,108 +1,434 @@
-use chrono::{DateTime, TimeZone, Utc};
+use crate::{AdminCommand, ExplainRow, ServerInfo};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+
+/// Matches `# Time:` header lines, returning the header value whether or not
+/// it is a valid timestamp.
+pub(crate) fn match_time(line: &str) -> Option<&str> {
+    Some(line.strip_prefix("# Time:")?.trim())
+}
 
 /// Parses timestamps from log entries.
-/// Format: "# Time: 2024-01-01T12:00:00.000000Z"
+/// Format: "# Time: 2024-01-01T12:00:00.123456Z" or "# Time: 2024-01-01T12:00:00.123456+02:00"
+/// Legacy format (MySQL 5.1 to 5.6, MariaDB): "# Time: 240101 12:00:00"
+///
+/// Fractional seconds are kept up to nanosecond precision, and numeric UTC
+/// offsets (written with `log_timestamps=SYSTEM`) are converted to UTC. Legacy
+/// headers carry no offset and are taken to be UTC.
 pub(crate) fn parse_timestamp(line: &str) -> Option<DateTime<Utc>> {
-    let line = line.strip_prefix("# Time: ")?;
-    let mut parts = line.split(['-', 'T', ':', '.']);
+    let value = match_time(line)?;
+    parse_iso_timestamp(value).or_else(|| parse_legacy_timestamp(value))
+}
 
+/// Parses an ISO 8601 timestamp as written by MySQL 5.7 and later.
+pub(crate) fn parse_iso_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let (date, time) = value.split_once('T')?;
+
+    let mut parts = date.split('-');
     let year = parts.next()?.parse::<i32>().ok()?;
     let month = parts.next()?.parse::<u32>().ok()?;
     let day = parts.next()?.parse::<u32>().ok()?;
+
+    let (time, offset) = split_utc_offset(time)?;
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+
+    let mut parts = time.split(':');
+    let hour = parts.next()?.parse::<u32>().ok()?;
+    let minute = parts.next()?.parse::<u32>().ok()?;
+    let second = parts.next()?.parse::<u32>().ok()?;
+    let nanos = parse_fraction(fraction)?;
+
+    NaiveDate::from_ymd_opt(year, month, day)?
+        .and_hms_nano_opt(hour, minute, second, nanos)?
+        .and_local_timezone(offset)
+        .single()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Parses a `YYMMDD H:MM:SS` timestamp as written by older servers. The hour
+/// is padded with a space rather than a zero, e.g. "240101  9:05:00".
+fn parse_legacy_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let mut parts = value.split_whitespace();
+    let date = parts.next()?;
+    let time = parts.next()?;
+    if parts.next().is_some() || date.len() != 6 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let year = date[..2].parse::<i32>().ok()?;
+    let year = if year < 70 { 2000 + year } else { 1900 + year };
+    let month = date[2..4].parse::<u32>().ok()?;
+    let day = date[4..].parse::<u32>().ok()?;
+
+    let mut parts = time.split(':');
     let hour = parts.next()?.parse::<u32>().ok()?;
     let minute = parts.next()?.parse::<u32>().ok()?;
     let second = parts.next()?.parse::<u32>().ok()?;
 
     Some(
-        Utc.with_ymd_and_hms(year, month, day, hour, minute, second)
-            .unwrap(),
+        NaiveDate::from_ymd_opt(year, month, day)?
+            .and_hms_opt(hour, minute, second)?
+            .and_utc(),
     )
 }
 
-/// Parses user and host from log entries.
-/// Format: "# User@Host: user[user] @  [192.168.1.100]  Id: 541085"
-pub(crate) fn parse_user_host(line: &str) -> Option<(String, String)> {
+/// Splits a trailing `Z` or `+HH:MM`/`-HH:MM` offset from a time of day.
+/// A time without a suffix is taken to be UTC.
+fn split_utc_offset(time: &str) -> Option<(&str, FixedOffset)> {
+    if let Some(time) = time.strip_suffix('Z') {
+        return Some((time, FixedOffset::east_opt(0)?));
+    }
+
+    let Some(pos) = time.rfind(['+', '-']) else {
+        return Some((time, FixedOffset::east_opt(0)?));
+    };
+    let (time, offset) = time.split_at(pos);
+    let (hours, minutes) = offset[1..].split_once(':').unwrap_or((&offset[1..], "0"));
+    let seconds = hours
+        .parse::<i32>()
+        .ok()?
+        .checked_mul(3600)?
+        .checked_add(minutes.parse::<i32>().ok()?.checked_mul(60)?)?;
+    let offset = if offset.starts_with('-') {
+        FixedOffset::west_opt(seconds)?
+    } else {
+        FixedOffset::east_opt(seconds)?
+    };
+
+    Some((time, offset))
+}
+
+/// Converts the digits after the decimal point of a seconds value into
+/// nanoseconds, ignoring any digits beyond nanosecond precision.
+fn parse_fraction(fraction: &str) -> Option<u32> {
+    if fraction.is_empty() {
+        return Some(0);
+    }
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = &fraction[..fraction.len().min(9)];
+    let scale = 10u32.pow(9 - u32::try_from(digits.len()).ok()?);
+    Some(digits.parse::<u32>().ok()? * scale)
+}
+
+/// Client identity fields from a `# User@Host:` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UserHost {
+    pub user: String,
+    pub auth_user: String,
+    pub host: String,
+    pub hostname: String,
+    pub thread_id: Option<u64>,
+}
+
+/// Parses user, host and connection id from log entries.
+/// Format: "# User@Host: priv_user[user] @ hostname [192.168.1.100]  Id: 541085"
+///
+/// The part before the brackets is the effective (privilege) user and the
+/// bracketed part is the user that authenticated, which differs for proxy
+/// users. `host` is the bracketed IP and `hostname` the resolved name before
+/// it; either may be empty. The `Id:` suffix is optional; MariaDB writes the
+/// id on a separate line.
+pub(crate) fn parse_user_host(line: &str) -> Option<UserHost> {
     let start = line.find("User@Host: ")? + "User@Host: ".len();
     let rest = &line[start..];
 
     let at_pos = rest.find(" @ ")?;
-    let user = rest[..at_pos].to_string();
+    let users = rest[..at_pos].trim();
+    let (user, auth_user) = match users.split_once('[') {
+        Some((user, auth_user)) => (user, auth_user.trim_end_matches(']')),
+        None => (users, users),
+    };
 
     let rest = &rest[at_pos + 3..];
     let bracket_start = rest.find('[')?;
-    let bracket_end = rest.find(']')?;
-    let host = rest[bracket_start + 1..bracket_end].to_string();
+    let bracket_end = bracket_start + rest[bracket_start..].find(']')?;
+    let hostname = rest[..bracket_start].trim();
+    let host = &rest[bracket_start + 1..bracket_end];
+
+    let rest = &rest[bracket_end + 1..];
+    let thread_id = rest
+        .find("Id:")
+        .and_then(|pos| rest[pos + "Id:".len()..].trim().parse().ok());
 
-    Some((user, host))
+    Some(UserHost {
+        user: user.to_string(),
+        auth_user: auth_user.to_string(),
+        host: host.to_string(),
+        hostname: hostname.to_string(),
+        thread_id,
+    })
 }
 
-/// Parses query stats from log entries.
-/// Format: "# `Query_time`: 0.997582  `Lock_time`: 0.000284 `Rows_sent`: 1  `Rows_examined`: 410716"
-pub(crate) fn parse_query_stats(line: &str) -> Option<(f64, f64, u64, u64)> {
-    fn extract_value<'a>(line: &'a str, field: &str) -> Option<&'a str> {
-        let start = line.find(field)? + field.len();
-        let rest = &line[start..].trim_start();
-        let end = rest.find(|c: char| c.is_whitespace()).unwrap_or(rest.len());
-        Some(&rest[..end])
+/// Splits a header line into its `Key: value` fields.
+/// Format: "# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 5 Thread_id: 10"
+///
+/// A key followed directly by another key has an empty value.
+pub(crate) fn parse_fields(line: &str) -> Vec<(&str, &str)> {
+    let mut fields = Vec::new();
+    let mut tokens = line.trim_start_matches('#').split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let Some(key) = token.strip_suffix(':') else {
+            continue;
+        };
+        let value = tokens.next_if(|next| !next.ends_with(':')).unwrap_or("");
+        fields.push((key, value));
     }
+    fields
+}
 
-    let query_time = extract_value(line, "Query_time:")?.parse().ok()?;
-    let lock_time = extract_value(line, "Lock_time:")?.parse().ok()?;
-    let rows_sent = extract_value(line, "Rows_sent:")?.parse().ok()?;
-    let rows_examined = extract_value(line, "Rows_examined:")?.parse().ok()?;
+/// Parses the `Yes`/`No` flags Percona Server writes, e.g. `Full_scan: Yes`.
+pub(crate) fn parse_yes_no(value: &str) -> Option<bool> {
+    match value {
+        "Yes" => Some(true),
+        "No" => Some(false),
+        _ => None,
+    }
+}
 
-    Some((query_time, lock_time, rows_sent, rows_examined))
+/// Parses the tab-separated cells of a MariaDB `EXPLAIN` line.
+/// Format: "# explain: id\tselect_type\ttable\ttype\t..."
+pub(crate) fn parse_explain(line: &str) -> Option<Vec<&str>> {
+    let rest = line.strip_prefix("# explain: ")?;
+    Some(rest.split('\t').map(str::trim).collect())
 }
 
-pub(crate) fn match_bin(line: &str) -> bool {
-    line.starts_with('/')
+/// Builds an [`ExplainRow`] from the cells of an `EXPLAIN` line, using the
+/// column names from the block's header line.
+pub(crate) fn explain_row(columns: &[String], cells: &[&str]) -> ExplainRow {
+    let mut row = ExplainRow::default();
+    for (column, cell) in columns.iter().zip(cells) {
+        let value = (!cell.is_empty() && *cell != "NULL").then(|| (*cell).to_string());
+        match column.as_str() {
+            "id" => row.id = cell.parse().ok(),
+            "select_type" => row.select_type = value,
+            "table" => row.table = value,
+            "type" => row.join_type = value,
+            "possible_keys" => row.possible_keys = value,
+            "key" => row.key = value,
+            "key_len" => row.key_len = value,
+            "ref" => row.reference = value,
+            "rows" => row.rows = cell.parse().ok(),
+            "r_rows" => row.r_rows = cell.parse().ok(),
+            "filtered" => row.filtered = cell.parse().ok(),
+            "r_filtered" => row.r_filtered = cell.parse().ok(),
+            "Extra" => row.extra = value,
+            _ => {}
+        }
+    }
+    row
 }
 
-pub(crate) fn match_set(line: &str) -> bool {
-    if let Some(rest) = line.strip_prefix("SET ") {
-        rest.starts_with("last_insert_id")
-            || rest.starts_with("insert_id")
-            || rest.starts_with("timestamp")
-    } else {
-        false
+/// Parses administrator command lines, which take the place of the query.
+/// Format: "# administrator command: Quit;" or "# administrator command: Close stmt;"
+pub(crate) fn parse_admin_command(line: &str) -> Option<AdminCommand> {
+    let name = line.strip_prefix("# administrator command:")?;
+    let name = name.trim().trim_end_matches(';').trim_end();
+    Some(AdminCommand::from_name(name))
+}
+
+/// Matches `#` header lines. Every such line is metadata, including unknown
+/// headers and lines without `Key: value` fields, such as Percona Server's
+/// "# No InnoDB statistics available for this query".
+pub(crate) fn match_metadata(line: &str) -> bool {
+    line.starts_with('#')
+}
+
+/// Parses the first line of the banner written when the server (re)opens the
+/// log.
+/// Format: "/usr/sbin/mysqld, Version: 8.0.26 (MySQL Community Server - GPL). started with:"
+///
+/// Only matches a path to the server binary followed by `, Version:`, so
+/// query lines starting with `/* comment */` are not mistaken for it.
+pub(crate) fn parse_bin(line: &str) -> Option<ServerInfo> {
+    let (path, rest) = line.split_once(", Version: ")?;
+    let unix = path.starts_with('/') && !path.contains(char::is_whitespace);
+    let windows = path.get(1..3) == Some(":\\");
+    if !unix && !windows {
+        return None;
     }
+
+    let rest = rest.trim_end();
+    let rest = rest
+        .strip_suffix("started with:")
+        .unwrap_or(rest)
+        .trim_end();
+    let rest = rest.strip_suffix('.').unwrap_or(rest);
+    let (version, comment) = match rest.split_once(" (") {
+        Some((version, comment)) => (version, comment.strip_suffix(')').unwrap_or(comment)),
+        None => (rest, ""),
+    };
+    Some(ServerInfo {
+        binary: path.to_string(),
+        version: version.trim().to_string(),
+        version_comment: comment.to_string(),
+        ..ServerInfo::default()
+    })
+}
+
+/// Parses the port and socket from the second banner line.
+/// Format: "Tcp port: 3306  Unix socket: /tmp/mysql.sock" or, on Windows,
+/// "Tcp port: 0  Named Pipe: MySQL"
+pub(crate) fn parse_tcp(line: &str) -> Option<(Option<u16>, Option<String>)> {
+    let rest = line.strip_prefix("Tcp port:")?;
+    let (port, socket) = match rest.split_once("Unix socket:") {
+        Some(parts) => parts,
+        None => rest.split_once("Named Pipe:").unwrap_or((rest, "")),
+    };
+    let socket = socket.trim();
+    Some((
+        port.trim().parse().ok(),
+        (!socket.is_empty()).then(|| socket.to_string()),
+    ))
+}
+
+/// Parses the session context MySQL writes before each statement.
+/// Format: "SET last_insert_id=5,insert_id=6,timestamp=1625097600;"
+///
+/// Returns `None` unless every assignment is one of `timestamp`, `insert_id`
+/// or `last_insert_id`, so `SET` statements that are themselves slow queries
+/// are left alone.
+pub(crate) fn parse_set(line: &str) -> Option<Vec<(&str, &str)>> {
+    let rest = line.strip_prefix("SET ")?.trim_end().strip_suffix(';')?;
+    rest.split(',')
+        .map(|assignment| {
+            let (name, value) = assignment.split_once('=')?;
+            matches!(name.trim(), "timestamp" | "insert_id" | "last_insert_id")
+                .then(|| (name.trim(), value.trim()))
+        })
+        .collect()
+}
+
+/// Parses a Unix timestamp with optional fractional seconds.
+/// Format: "1625097600" or "1625097600.123456"
+pub(crate) fn parse_unix_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    DateTime::from_timestamp(seconds.parse().ok()?, parse_fraction(fraction)?)
 }
 
-pub(crate) fn match_use(line: &str) -> bool {
-    line.len() >= 4 && line[..4].eq_ignore_ascii_case("use ")
+/// Parses the database name from `USE` lines.
+/// Format: "use shop;" or "USE `shop`;"
+pub(crate) fn parse_use(line: &str) -> Option<String> {
+    if !line
+        .get(..4)
+        .is_some_and(|p| p.eq_ignore_ascii_case("use "))
+    {
+        return None;
+    }
+
+    let schema = line[4..].trim().trim_end_matches(';').trim();
+    let schema = schema
+        .strip_prefix('`')
+        .and_then(|schema| schema.strip_suffix('`'))
+        .unwrap_or(schema);
+    Some(schema.to_string())
 }
 
+/// Matches the remaining banner lines after [`parse_bin`].
+/// Format: "Tcp port: 3306  Unix socket: /tmp/mysql.sock" and
+/// "Time                 Id Command    Argument"
 pub(crate) fn match_tcp(line: &str) -> bool {
-    (line.len() >= 3 && line[..3].eq_ignore_ascii_case("Tcp"))
-        || (line.len() >= 4 && line[..4].eq_ignore_ascii_case("Time"))
+    line.starts_with("Tcp port:")
+        || line
+            .split_whitespace()
+            .eq(["Time", "Id", "Command", "Argument"])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{TimeDelta, TimeZone};
 
     #[test]
-    fn test_match_bin() {
+    fn test_parse_bin() {
         let line = "/rdsdbbin/oscar/bin/mysqld, Version: 5.7.12-log (MySQL Community Server (GPL)). started with:";
-        assert!(match_bin(line));
+        let server = parse_bin(line).unwrap();
+        assert_eq!(server.binary, "/rdsdbbin/oscar/bin/mysqld");
+        assert_eq!(server.version, "5.7.12-log");
+        assert_eq!(server.version_comment, "MySQL Community Server (GPL)");
+        let line = r"C:\Program Files\MySQL\MySQL Server 8.0\bin\mysqld.exe, Version: 8.0.26 (MySQL Community Server - GPL). started with:";
+        assert!(parse_bin(line).is_some());
+        assert_eq!(
+            parse_bin("/usr/sbin/mysqld, Version: 8.0.0").map(|server| server.version),
+            Some("8.0.0".to_string())
+        );
+        assert_eq!(
+            parse_bin("/* caller: OrderRepository::find */ SELECT * FROM orders"),
+            None
+        );
+        assert_eq!(
+            parse_bin("/* list */ SELECT name, Version: 1 FROM packages"),
+            None
+        );
     }
 
     #[test]
-    fn test_match_set() {
+    fn test_parse_tcp() {
+        assert_eq!(
+            parse_tcp("Tcp port: 3306  Unix socket: /tmp/mysql.sock"),
+            Some((Some(3306), Some("/tmp/mysql.sock".to_string())))
+        );
+        assert_eq!(
+            parse_tcp("Tcp port: 0  Named Pipe: MySQL"),
+            Some((Some(0), Some("MySQL".to_string())))
+        );
+        assert_eq!(
+            parse_tcp("Time                 Id Command    Argument"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_set() {
         let line = "SET timestamp=1625097600;";
-        assert!(match_set(line));
+        assert_eq!(parse_set(line), Some(vec![("timestamp", "1625097600")]));
+    }
+
+    #[test]
+    fn test_parse_set_insert_ids() {
+        let line = "SET last_insert_id=41,insert_id=42,timestamp=1625097600;";
+        assert_eq!(
+            parse_set(line),
+            Some(vec![
+                ("last_insert_id", "41"),
+                ("insert_id", "42"),
+                ("timestamp", "1625097600"),
+            ])
+        );
+        assert_eq!(parse_set("SET autocommit=0;"), None);
+        assert_eq!(parse_set("SET timestamp=1625097600, autocommit=0;"), None);
+    }
+
+    #[test]
+    fn test_parse_unix_timestamp() {
+        assert_eq!(
+            parse_unix_timestamp("1625097600"),
+            Some(Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_unix_timestamp("1625097600.250000"),
+            Some(Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 0).unwrap() + TimeDelta::milliseconds(250))
+        );
+        assert_eq!(parse_unix_timestamp("soon"), None);
     }
 
     #[test]
-    fn test_match_use() {
+    fn test_parse_use() {
         let line = "USE `test`;";
-        assert!(match_use(line));
+        assert_eq!(parse_use(line).as_deref(), Some("test"));
+        assert_eq!(parse_use("use shop").as_deref(), Some("shop"));
+        assert_eq!(parse_use("SELECT 1"), None);
+        assert_eq!(parse_use("日本語 AS x FROM t;"), None);
     }
 
     #[test]
     fn test_match_tcp() {
         let line = "Tcp port: 3306  Unix socket: /tmp/mysql.sock";
         assert!(match_tcp(line));
+        assert!(match_tcp("Time                 Id Command    Argument"));
+        assert!(!match_tcp("timestamp > NOW() - INTERVAL 1 DAY"));
+        assert!(!match_tcp("TIME_TO_SEC(duration) > 60"));
     }
 
     #[test]
@@ -115,22 +441,236 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_timestamp_microseconds() {
+        let line = "# Time: 2024-01-01T12:00:00.123456Z";
+        let timestamp = parse_timestamp(line).unwrap();
+        assert_eq!(
+            timestamp,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() + TimeDelta::microseconds(123_456)
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_utc_offset() {
+        let line = "# Time: 2024-01-01T12:00:00.123456+02:00";
+        let timestamp = parse_timestamp(line).unwrap();
+        assert_eq!(
+            timestamp,
+            Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap() + TimeDelta::microseconds(123_456)
+        );
+
+        let line = "# Time: 2024-01-01T01:30:00-05:30";
+        let timestamp = parse_timestamp(line).unwrap();
+        assert_eq!(
+            timestamp,
+            Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_without_fraction() {
+        let line = "# Time: 2024-01-01T12:00:00Z";
+        let timestamp = parse_timestamp(line).unwrap();
+        assert_eq!(
+            timestamp,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_legacy() {
+        let line = "# Time: 240101 12:00:00";
+        let timestamp = parse_timestamp(line).unwrap();
+        assert_eq!(
+            timestamp,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+        );
+
+        let line = "# Time: 150106  9:05:07";
+        let timestamp = parse_timestamp(line).unwrap();
+        assert_eq!(
+            timestamp,
+            Utc.with_ymd_and_hms(2015, 1, 6, 9, 5, 7).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_invalid_date() {
+        assert_eq!(parse_timestamp("# Time: 2024-13-45T99:00:00Z"), None);
+        assert_eq!(parse_timestamp("# Time: 240230 12:00:00"), None);
+        assert_eq!(
+            match_time("# Time: 2024-13-45T99:00:00Z"),
+            Some("2024-13-45T99:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_without_space() {
+        let line = "# Time:2024-01-01T12:00:00Z";
+        assert_eq!(match_time(line), Some("2024-01-01T12:00:00Z"));
+        assert_eq!(
+            parse_timestamp(line),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_invalid_offset() {
+        assert_eq!(
+            parse_timestamp("# Time: 2024-01-01T12:00:00+999999:00"),
+            None
+        );
+        assert_eq!(
+            parse_timestamp("# Time: 2024-01-01T12:00:00-00:99999999"),
+            None
+        );
+        assert_eq!(parse_timestamp("# Time: 2024-01-01T12:00:00+25:00"), None);
+    }
+
     #[test]
     fn test_parse_user_host() {
         let line = "# User@Host: user[user] @  [192.168.89.201]  Id: 541085";
-        let (user, host) = parse_user_host(line).unwrap();
-        assert_eq!(user, "user[user]");
-        assert_eq!(host, "192.168.89.201");
+        let user_host = parse_user_host(line).unwrap();
+        assert_eq!(user_host.user, "user");
+        assert_eq!(user_host.auth_user, "user");
+        assert_eq!(user_host.hostname, "");
+        assert_eq!(user_host.host, "192.168.89.201");
+        assert_eq!(user_host.thread_id, Some(541_085));
+    }
+
+    #[test]
+    fn test_parse_user_host_without_id() {
+        let line = "# User@Host: user[user] @ localhost [127.0.0.1]";
+        let user_host = parse_user_host(line).unwrap();
+        assert_eq!(user_host.hostname, "localhost");
+        assert_eq!(user_host.host, "127.0.0.1");
+        assert_eq!(user_host.thread_id, None);
     }
 
     #[test]
-    fn test_parse_query_stats() {
+    fn test_parse_user_host_hostname_and_proxy() {
+        let line = "# User@Host: app[app] @ web01.internal [10.0.0.5]  Id:    12";
+        let user_host = parse_user_host(line).unwrap();
+        assert_eq!(user_host.user, "app");
+        assert_eq!(user_host.hostname, "web01.internal");
+        assert_eq!(user_host.host, "10.0.0.5");
+        assert_eq!(user_host.thread_id, Some(12));
+
+        let line = "# User@Host: reporting[alice] @ localhost []";
+        let user_host = parse_user_host(line).unwrap();
+        assert_eq!(user_host.user, "reporting");
+        assert_eq!(user_host.auth_user, "alice");
+        assert_eq!(user_host.hostname, "localhost");
+        assert_eq!(user_host.host, "");
+    }
+
+    #[test]
+    fn test_parse_fields_query_time() {
         let line =
             "# Query_time: 0.997582  Lock_time: 0.000284 Rows_sent: 1  Rows_examined: 410716";
-        let (query_time, lock_time, rows_sent, rows_examined) = parse_query_stats(line).unwrap();
-        assert!((query_time - 0.997_582).abs() < f64::EPSILON);
-        assert!((lock_time - 0.000_284).abs() < f64::EPSILON);
-        assert_eq!(rows_sent, 1);
-        assert_eq!(rows_examined, 410_716);
+        assert_eq!(
+            parse_fields(line),
+            vec![
+                ("Query_time", "0.997582"),
+                ("Lock_time", "0.000284"),
+                ("Rows_sent", "1"),
+                ("Rows_examined", "410716"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_admin_command() {
+        let line = "# administrator command: Quit;";
+        assert_eq!(parse_admin_command(line), Some(AdminCommand::Quit));
+        let line = "# administrator command: Close stmt;";
+        assert_eq!(parse_admin_command(line), Some(AdminCommand::CloseStmt));
+        let line = "# administrator command: Daemon;";
+        assert_eq!(
+            parse_admin_command(line),
+            Some(AdminCommand::Other("Daemon".to_string()))
+        );
+        assert_eq!(parse_admin_command("# Query_time: 1.0"), None);
+    }
+
+    #[test]
+    fn test_match_metadata() {
+        assert!(match_metadata(
+            "# No InnoDB statistics available for this query"
+        ));
+        assert!(match_metadata("#"));
+        assert!(!match_metadata("SELECT 1 # trailing comment"));
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let line =
+            "# Query_time: 1.5  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 5 Thread_id: 10 \
+                    Errno: 0 Start: 2021-07-01T00:00:00.000000Z";
+        let fields = parse_fields(line);
+        assert_eq!(
+            fields,
+            vec![
+                ("Query_time", "1.5"),
+                ("Lock_time", "0.0"),
+                ("Rows_sent", "1"),
+                ("Rows_examined", "5"),
+                ("Thread_id", "10"),
+                ("Errno", "0"),
+                ("Start", "2021-07-01T00:00:00.000000Z"),
+            ]
+        );
+
+        assert_eq!(
+            parse_fields("# Thread_id: 42  Schema: shop  Last_errno: 0  Killed: 0"),
+            vec![
+                ("Thread_id", "42"),
+                ("Schema", "shop"),
+                ("Last_errno", "0"),
+                ("Killed", "0")
+            ]
+        );
+        assert_eq!(
+            parse_fields("# Thread_id: 2  Schema:   QC_hit: No"),
+            vec![("Thread_id", "2"), ("Schema", ""), ("QC_hit", "No")]
+        );
+    }
+
+    #[test]
+    fn test_parse_explain() {
+        let header = "# explain: id\tselect_type\ttable\ttype\tpossible_keys\tkey\tkey_len\tref\trows\tr_rows\tfiltered\tr_filtered\tExtra";
+        let columns: Vec<String> = parse_explain(header)
+            .unwrap()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(columns.len(), 13);
+
+        let line = "# explain: 1\tSIMPLE\tusers\tref\tidx_email\tidx_email\t767\tconst\t1\t1.00\t100.00\t100.00\tUsing where";
+        let row = explain_row(&columns, &parse_explain(line).unwrap());
+        assert_eq!(row.id, Some(1));
+        assert_eq!(row.table.as_deref(), Some("users"));
+        assert_eq!(row.join_type.as_deref(), Some("ref"));
+        assert_eq!(row.reference.as_deref(), Some("const"));
+        assert_eq!(row.rows, Some(1));
+        assert_eq!(row.r_filtered, Some(100.0));
+        assert_eq!(row.extra.as_deref(), Some("Using where"));
+
+        let line =
+            "# explain: 1\tSIMPLE\tt\tALL\tNULL\tNULL\tNULL\tNULL\t1000\t1000.00\t100.00\t10.00\t";
+        let row = explain_row(&columns, &parse_explain(line).unwrap());
+        assert_eq!(row.possible_keys, None);
+        assert_eq!(row.key, None);
+        assert_eq!(row.extra, None);
+
+        assert_eq!(parse_explain("# Full_scan: Yes"), None);
+    }
+
+    #[test]
+    fn test_parse_yes_no() {
+        assert_eq!(parse_yes_no("Yes"), Some(true));
+        assert_eq!(parse_yes_no("No"), Some(false));
+        assert_eq!(parse_yes_no("Maybe"), None);
     }
 }
@@ -0,0 +1,1244 @@
+use crate::{
+    helpers, lexical, sql, AdminCommand, Diagnostic, EntryError, Error, Flavour, Normaliser,
+    ParseOptions, Query, QueryKind, QueryStats, ServerInfo, Statement,
+};
+
+#[cfg(feature = "readers")]
+use std::io::{self, BufRead};
+
+/// Allowed disagreement, in seconds, between `SET timestamp` plus the query
+/// time and the `# Time:` header. Older servers write both with whole-second
+/// precision.
+const START_TIME_TOLERANCE: f64 = 1.0;
+
+/// An iterator over the entries of a MySQL slow query log.
+///
+/// Wraps any iterator of lines yielding `Result<S, E>` where `S: AsRef<str>`,
+/// and yields one `Result<Query, Error<E>>` per log entry.
+///
+/// Errors from the line source are yielded as [`Error::Read`]; iteration may
+/// continue afterwards if the source allows it. Entries the SQL parser cannot
+/// handle are normalised with a token-based fallback (see [`Normaliser`]); only
+/// entries that fail both are yielded as [`Error::Entry`], carrying the raw SQL,
+/// the stats and the line on which the entry started, and iteration continues
+/// with the next entry.
+///
+/// Because it is a regular [`Iterator`], adapters such as `take`, `filter` and
+/// `zip` work as usual and processing can stop early by dropping the iterator.
+///
+/// # Examples
+///
+/// ```
+/// use slowlog::SlowLogIter;
+///
+/// let data = "# Time: 2024-01-01T00:00:00.000000Z
+/// ## User@Host: user[user] @  [127.0.0.1]
+/// ## Query_time: 1.5  Lock_time: 0.1 Rows_sent: 10  Rows_examined: 1000
+/// SELECT * FROM users WHERE id = 1;
+/// ## Time: 2024-01-01T00:00:01.000000Z
+/// ## User@Host: user[user] @  [127.0.0.1]
+/// ## Query_time: 0.5  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+/// SELECT 1;";
+///
+/// let slow: Vec<_> = SlowLogIter::new(data.lines().map(Ok::<_, std::convert::Infallible>))
+///     .filter_map(Result::ok)
+///     .filter(|query| query.stats.query_time > 1.0)
+///     .collect();
+///
+/// assert_eq!(slow.len(), 1);
+/// assert_eq!(slow[0].formatted, "SELECT * FROM users WHERE id = ?");
+/// ```
+#[derive(Debug)]
+pub struct SlowLogIter<I> {
+    lines: I,
+    options: ParseOptions,
+    line_no: usize,
+    entry_line: Option<usize>,
+    query: String,
+    stats: QueryStats,
+    has_time: bool,
+    has_header: bool,
+    diagnostics: Vec<Diagnostic>,
+    explain_columns: Vec<String>,
+    server: Option<ServerInfo>,
+    command: Option<AdminCommand>,
+    finished: bool,
+}
+
+impl<I> SlowLogIter<I> {
+    /// Creates an iterator over the entries found in `lines`.
+    pub fn new(lines: I) -> Self {
+        Self {
+            lines,
+            options: ParseOptions::default(),
+            line_no: 0,
+            entry_line: None,
+            query: String::new(),
+            stats: QueryStats::default(),
+            has_time: false,
+            has_header: false,
+            diagnostics: Vec::new(),
+            explain_columns: Vec::new(),
+            server: None,
+            command: None,
+            finished: false,
+        }
+    }
+
+    /// Sets the options used for the remaining entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use slowlog::{ParseOptions, SlowLogIter};
+    ///
+    /// let options = ParseOptions {
+    ///     schema_in_fingerprint: true,
+    ///     ..ParseOptions::default()
+    /// };
+    /// let data = "use shop;\nSELECT * FROM users;\n# User@Host: app[app] @  []\nuse crm;\nSELECT * FROM users;";
+    /// let entries: Vec<_> = SlowLogIter::new(data.lines().map(Ok::<_, std::convert::Infallible>))
+    ///     .with_options(options)
+    ///     .map(Result::unwrap)
+    ///     .collect();
+    ///
+    /// assert_eq!(entries[0].stats.schema.as_deref(), Some("shop"));
+    /// assert_ne!(entries[0].fingerprint, entries[1].fingerprint);
+    /// ```
+    #[must_use]
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Handles a single log line, returning the previous entry if the line
+    /// starts a new one.
+    fn process_line(&mut self, line: &str) -> Option<Result<Query, EntryError>> {
+        // `USE` and `SET` context lines come before the statement; inside a
+        // body they are part of it, e.g. `USE INDEX (...)` or `SET a = 1`. A
+        // command entry has no body, so they begin the next entry
+        let in_body = !self.query.is_empty() && self.command.is_none();
+
+        if let Some(schema) = helpers::parse_use(line).filter(|_| !in_body) {
+            let query = self.flush_command();
+            self.stats.schema = Some(schema);
+            return query;
+        }
+
+        if let Some(variables) = helpers::parse_set(line).filter(|_| !in_body) {
+            let query = self.flush_command();
+            for (name, value) in variables {
+                match name {
+                    // `Start:` from `log_slow_extra` is more precise
+                    "timestamp" if self.stats.start_time.is_none() => {
+                        self.stats.start_time = helpers::parse_unix_timestamp(value);
+                    }
+                    "insert_id" => self.stats.insert_id = value.parse().ok(),
+                    "last_insert_id" => self.stats.last_insert_id = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            return query;
+        }
+
+        // A startup banner ends the previous server run
+        if let Some(server) = helpers::parse_bin(line) {
+            let query = self.flush();
+            self.server = Some(server);
+            return query;
+        }
+
+        if let Some((port, socket)) = helpers::parse_tcp(line) {
+            let server = self.server.get_or_insert_with(ServerInfo::default);
+            server.port = port;
+            server.socket = socket;
+            return None;
+        }
+
+        if helpers::match_tcp(line) {
+            return None;
+        }
+
+        if let Some(value) = helpers::match_time(line) {
+            let query = self.flush();
+            self.entry_line = Some(self.line_no);
+            let timestamp = helpers::parse_timestamp(line);
+            self.has_time = timestamp.is_some();
+            match timestamp {
+                Some(timestamp) => self.stats.time = timestamp,
+                None => self.diagnostics.push(Diagnostic::InvalidTimestamp {
+                    line: self.line_no,
+                    value: value.to_string(),
+                }),
+            }
+            return query;
+        }
+
+        if let Some(user_host) = helpers::parse_user_host(line) {
+            // A preceding `# Time:` line on its own already started this entry
+            let query = if in_body || self.has_header {
+                self.flush()
+            } else {
+                None
+            };
+            self.has_header = true;
+            self.entry_line.get_or_insert(self.line_no);
+            self.stats.user = user_host.user;
+            self.stats.auth_user = user_host.auth_user;
+            self.stats.host = user_host.host;
+            self.stats.hostname = user_host.hostname;
+            self.stats.thread_id = user_host.thread_id;
+            return query;
+        }
+
+        if let Some(command) = helpers::parse_admin_command(line) {
+            // A command cannot continue an SQL body, so that body is complete
+            let query = if in_body { self.flush() } else { None };
+            self.entry_line.get_or_insert(self.line_no);
+            self.command = Some(command);
+            self.query = line.trim().to_string();
+            return query;
+        }
+
+        // Any other `#` line is header metadata, whether or not its fields are
+        // recognised, so it never becomes part of the query text
+        if helpers::match_metadata(line) {
+            self.has_header = true;
+            self.entry_line.get_or_insert(self.line_no);
+            // `# explain:` rows are only read as MariaDB writes them; they
+            // hold no `Key: value` fields for the other flavours
+            match helpers::parse_explain(line) {
+                Some(cells) => {
+                    if self.is_mariadb() {
+                        self.apply_explain(&cells);
+                    }
+                }
+                None => self.apply_fields(line),
+            }
+            return None;
+        }
+
+        let query = if line.trim().is_empty() {
+            None
+        } else {
+            self.flush_command()
+        };
+        self.entry_line.get_or_insert(self.line_no);
+        self.query.push(' ');
+        self.query.push_str(line);
+        query
+    }
+
+    /// Ends an administrator command entry. A command entry is its single
+    /// header line, so any text after it belongs to a new entry.
+    fn flush_command(&mut self) -> Option<Result<Query, EntryError>> {
+        if self.command.is_some() {
+            self.flush()
+        } else {
+            None
+        }
+    }
+
+    /// Formats the accumulated query text into a [`Query`].
+    ///
+    /// Called at every entry boundary (`# Time:`, `# User@Host:` or a startup
+    /// banner) and at the end of input. Clears the entry's state so the next
+    /// entry starts empty, even when it had headers but no query text, which
+    /// are then dropped.
+    fn flush(&mut self) -> Option<Result<Query, EntryError>> {
+        let query = core::mem::take(&mut self.query);
+        let line = self.entry_line.take().unwrap_or(self.line_no);
+        let mut diagnostics = core::mem::take(&mut self.diagnostics);
+        diagnostics.extend(self.check_start_time());
+        self.has_time = false;
+        self.has_header = false;
+
+        // Header fields and `SET` session context describe one statement;
+        // only the entry time and current schema carry over
+        let next = QueryStats {
+            time: self.stats.time,
+            schema: self.stats.schema.clone(),
+            ..QueryStats::default()
+        };
+        let stats = core::mem::replace(&mut self.stats, next);
+        let command = self.command.take();
+
+        if query.is_empty() {
+            return None;
+        }
+        let query = query.trim().to_string();
+
+        let statements = match &command {
+            Some(command) => Ok((
+                vec![format!("administrator command: {command}")],
+                Normaliser::Command,
+            )),
+            None => sql::format_statements(&query)
+                .map(|statements| (statements, Normaliser::Parser))
+                .or_else(|error| {
+                    lexical::normalise_query(&query)
+                        .map(|formatted| (vec![formatted], Normaliser::Lexical))
+                        .map_err(|_| error)
+                }),
+        };
+
+        Some(match statements {
+            Ok((statements, normaliser)) => {
+                let schema = stats
+                    .schema
+                    .as_deref()
+                    .filter(|_| self.options.schema_in_fingerprint && command.is_none());
+                let fingerprint = |formatted: &str| match schema {
+                    Some(schema) => sql::fingerprint_query(&format!("USE {schema}; {formatted}")),
+                    None => sql::fingerprint_query(formatted),
+                };
+                let formatted = statements.join("; ");
+                let statements = statements
+                    .into_iter()
+                    .map(|formatted| Statement {
+                        fingerprint: fingerprint(&formatted),
+                        formatted,
+                    })
+                    .collect();
+                Ok(Query {
+                    query,
+                    fingerprint: fingerprint(&formatted),
+                    formatted,
+                    statements,
+                    normaliser,
+                    kind: command.map_or(QueryKind::Sql, QueryKind::Admin),
+                    stats,
+                    diagnostics,
+                    server: self.server.clone(),
+                })
+            }
+            Err(error) => Err(EntryError {
+                error,
+                query,
+                stats,
+                line,
+                diagnostics,
+            }),
+        })
+    }
+
+    /// Returns true if header lines should be read as MariaDB writes them.
+    fn is_mariadb(&self) -> bool {
+        match self.options.flavour {
+            Flavour::Auto => self.server.as_ref().is_some_and(ServerInfo::is_mariadb),
+            Flavour::MySql => false,
+            Flavour::MariaDb => true,
+        }
+    }
+
+    /// Applies every `Key: value` field of a `#` header line and records each in
+    /// [`QueryStats::extra`].
+    fn apply_fields(&mut self, line: &str) {
+        for (key, value) in helpers::parse_fields(line) {
+            self.apply_field(key, value);
+            self.stats.extra.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    /// Applies one `Key: value` field from a `#` header line. Keys that are
+    /// not recognised are left alone.
+    ///
+    /// Covers the four core `# Query_time:` fields, the fields MySQL's
+    /// `log_slow_extra` appends to that line and the extra header lines
+    /// Percona Server writes with `log_slow_verbosity`. Fields both servers
+    /// share, such as `Killed` and `Bytes_sent`, fill the same field of
+    /// [`QueryStats`].
+    fn apply_field(&mut self, key: &str, value: &str) {
+        let stats = &mut self.stats;
+        match key {
+            // Each is applied on its own, so a partial `# Query_time:` line
+            // keeps the fields it has
+            "Query_time" => stats.query_time = value.parse().unwrap_or(stats.query_time),
+            "Lock_time" => stats.lock_time = value.parse().unwrap_or(stats.lock_time),
+            "Rows_sent" => stats.rows_sent = value.parse().unwrap_or(stats.rows_sent),
+            "Rows_examined" => stats.rows_examined = value.parse().unwrap_or(stats.rows_examined),
+            "Schema" => stats.schema = (!value.is_empty()).then(|| value.to_string()),
+            "Thread_id" => stats.thread_id = value.parse().ok().or(stats.thread_id),
+            "Start" => stats.start_time = helpers::parse_iso_timestamp(value),
+            "End" => stats.end_time = helpers::parse_iso_timestamp(value),
+            "Errno" | "Last_errno" => stats.errno = value.parse().ok(),
+            "Killed" => stats.killed = value.parse().ok(),
+            "Bytes_received" => stats.bytes_received = value.parse().ok(),
+            "Bytes_sent" => stats.bytes_sent = value.parse().ok(),
+            "Read_first" => stats.read_first = value.parse().ok(),
+            "Read_last" => stats.read_last = value.parse().ok(),
+            "Read_key" => stats.read_key = value.parse().ok(),
+            "Read_next" => stats.read_next = value.parse().ok(),
+            "Read_prev" => stats.read_prev = value.parse().ok(),
+            "Read_rnd" => stats.read_rnd = value.parse().ok(),
+            "Read_rnd_next" => stats.read_rnd_next = value.parse().ok(),
+            "Sort_merge_passes" => stats.sort_merge_passes = value.parse().ok(),
+            "Sort_range_count" => stats.sort_range_count = value.parse().ok(),
+            "Sort_rows" => stats.sort_rows = value.parse().ok(),
+            "Sort_scan_count" => stats.sort_scan_count = value.parse().ok(),
+            "Created_tmp_disk_tables" => stats.created_tmp_disk_tables = value.parse().ok(),
+            "Created_tmp_tables" => stats.created_tmp_tables = value.parse().ok(),
+            _ if self.is_mariadb() => self.apply_mariadb_field(key, value),
+            _ => self.apply_percona_field(key, value),
+        }
+    }
+
+    /// Applies a field only Percona Server writes to [`QueryStats::percona`].
+    fn apply_percona_field(&mut self, key: &str, value: &str) {
+        let mut stats = self.stats.percona.clone().unwrap_or_default();
+        match key {
+            "Rows_affected" => stats.rows_affected = value.parse().ok(),
+            "Tmp_tables" => stats.tmp_tables = value.parse().ok(),
+            "Tmp_disk_tables" => stats.tmp_disk_tables = value.parse().ok(),
+            "Tmp_table_sizes" => stats.tmp_table_sizes = value.parse().ok(),
+            "QC_Hit" => stats.qc_hit = helpers::parse_yes_no(value),
+            "Full_scan" => stats.full_scan = helpers::parse_yes_no(value),
+            "Full_join" => stats.full_join = helpers::parse_yes_no(value),
+            "Tmp_table" => stats.tmp_table = helpers::parse_yes_no(value),
+            "Tmp_table_on_disk" => stats.tmp_table_on_disk = helpers::parse_yes_no(value),
+            "Filesort" => stats.filesort = helpers::parse_yes_no(value),
+            "Filesort_on_disk" => stats.filesort_on_disk = helpers::parse_yes_no(value),
+            "Merge_passes" => stats.merge_passes = value.parse().ok(),
+            "InnoDB_trx_id" => stats.innodb_trx_id = Some(value.to_string()),
+            "InnoDB_IO_r_ops" => stats.innodb_io_r_ops = value.parse().ok(),
+            "InnoDB_IO_r_bytes" => stats.innodb_io_r_bytes = value.parse().ok(),
+            "InnoDB_IO_r_wait" => stats.innodb_io_r_wait = value.parse().ok(),
+            "InnoDB_rec_lock_wait" => stats.innodb_rec_lock_wait = value.parse().ok(),
+            "InnoDB_queue_wait" => stats.innodb_queue_wait = value.parse().ok(),
+            "InnoDB_pages_distinct" => stats.innodb_pages_distinct = value.parse().ok(),
+            _ => return,
+        }
+        self.stats.percona = Some(stats);
+    }
+
+    /// Applies a field only MariaDB writes to [`QueryStats::mariadb`].
+    fn apply_mariadb_field(&mut self, key: &str, value: &str) {
+        let mut stats = self.stats.mariadb.clone().unwrap_or_default();
+        match key {
+            "Rows_affected" => stats.rows_affected = value.parse().ok(),
+            "QC_hit" => stats.qc_hit = helpers::parse_yes_no(value),
+            "Full_scan" => stats.full_scan = helpers::parse_yes_no(value),
+            "Full_join" => stats.full_join = helpers::parse_yes_no(value),
+            "Tmp_table" => stats.tmp_table = helpers::parse_yes_no(value),
+            "Tmp_table_on_disk" => stats.tmp_table_on_disk = helpers::parse_yes_no(value),
+            "Filesort" => stats.filesort = helpers::parse_yes_no(value),
+            "Filesort_on_disk" => stats.filesort_on_disk = helpers::parse_yes_no(value),
+            "Merge_passes" => stats.merge_passes = value.parse().ok(),
+            "Priority_queue" => stats.priority_queue = helpers::parse_yes_no(value),
+            "Tmp_tables" => stats.tmp_tables = value.parse().ok(),
+            "Tmp_disk_tables" => stats.tmp_disk_tables = value.parse().ok(),
+            "Pages_accessed" => stats.pages_accessed = value.parse().ok(),
+            "Pages_read" => stats.pages_read = value.parse().ok(),
+            "Pages_prefetched" => stats.pages_prefetched = value.parse().ok(),
+            "Pages_updated" => stats.pages_updated = value.parse().ok(),
+            "Undo_records_added" => stats.undo_records_added = value.parse().ok(),
+            "Old_rows_read" => stats.old_rows_read = value.parse().ok(),
+            "Pages_read_time" => stats.pages_read_time = value.parse().ok(),
+            "Engine_time" => stats.engine_time = value.parse().ok(),
+            _ => return,
+        }
+        self.stats.mariadb = Some(stats);
+    }
+
+    /// Applies one `# explain:` line. The first line of each block names the
+    /// columns and the lines after it are rows.
+    fn apply_explain(&mut self, cells: &[&str]) {
+        self.entry_line.get_or_insert(self.line_no);
+        if cells.first() == Some(&"id") {
+            self.explain_columns = cells.iter().map(ToString::to_string).collect();
+            return;
+        }
+        let row = helpers::explain_row(&self.explain_columns, cells);
+        let stats = self.stats.mariadb.get_or_insert_with(Default::default);
+        stats.explain.push(row);
+    }
+
+    /// Checks that the start time from `SET timestamp` plus the query time
+    /// agrees with the end time from the entry's own `# Time:` header.
+    /// Entries without a valid header of their own are not checked.
+    fn check_start_time(&self) -> Option<Diagnostic> {
+        let start = self.stats.start_time?;
+        if !self.has_time {
+            return None;
+        }
+
+        let end = self.stats.time;
+        let elapsed = (end - start).as_seconds_f64();
+        ((elapsed - self.stats.query_time).abs() > START_TIME_TOLERANCE)
+            .then_some(Diagnostic::TimeMismatch { start, end })
+    }
+}
+
+#[cfg(feature = "readers")]
+impl<R: BufRead> SlowLogIter<io::Lines<R>> {
+    /// Creates an iterator over the entries read from any `BufRead` source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use slowlog::SlowLogIter;
+    /// use std::io::BufReader;
+    ///
+    /// let data = b"# Time: 2024-01-01T00:00:00.000000Z
+    /// ## User@Host: user[user] @  [127.0.0.1]
+    /// ## Query_time: 1.5  Lock_time: 0.1 Rows_sent: 10  Rows_examined: 1000
+    /// SELECT * FROM users WHERE id = 1;";
+    ///
+    /// let mut entries = SlowLogIter::from_reader(BufReader::new(&data[..]));
+    /// let query = entries.next().unwrap().unwrap();
+    /// assert_eq!(query.stats.query_time, 1.5);
+    /// assert!(entries.next().is_none());
+    /// ```
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(reader.lines())
+    }
+}
+
+impl<I, S, E> Iterator for SlowLogIter<I>
+where
+    I: Iterator<Item = Result<S, E>>,
+    S: AsRef<str>,
+{
+    type Item = Result<Query, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let Some(line) = self.lines.next() else {
+                self.finished = true;
+                return self
+                    .flush()
+                    .map(|entry| entry.map_err(|err| Error::Entry(Box::new(err))));
+            };
+
+            self.line_no += 1;
+            match line {
+                Ok(line) => {
+                    if let Some(entry) = self.process_line(line.as_ref()) {
+                        return Some(entry.map_err(|err| Error::Entry(Box::new(err))));
+                    }
+                }
+                Err(e) => return Some(Err(Error::Read(e))),
+            }
+        }
+
+        None
+    }
+}
+
+impl<I, S, E> core::iter::FusedIterator for SlowLogIter<I>
+where
+    I: Iterator<Item = Result<S, E>>,
+    S: AsRef<str>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryError;
+    use chrono::{TimeZone, Utc};
+    use core::convert::Infallible;
+
+    const LOG: &str = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM users WHERE id = 1;
+# Time: 2024-01-01T12:01:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 2.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM products WHERE id = 2;
+# Time: 2024-01-01T12:02:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 3.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM orders WHERE id = 3;
+";
+
+    fn entries(data: &str) -> SlowLogIter<impl Iterator<Item = Result<&str, Infallible>>> {
+        SlowLogIter::new(data.lines().map(Ok))
+    }
+
+    #[test]
+    fn test_iter_yields_all_entries() {
+        let formatted: Vec<_> = entries(LOG).map(|q| q.unwrap().formatted).collect();
+        assert_eq!(
+            formatted,
+            [
+                "SELECT * FROM users WHERE id = ?",
+                "SELECT * FROM products WHERE id = ?",
+                "SELECT * FROM orders WHERE id = ?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_take_stops_early() {
+        let mut lines_read = 0;
+        let lines = LOG
+            .lines()
+            .inspect(|_| lines_read += 1)
+            .map(Ok::<_, Infallible>);
+        let first: Vec<_> = SlowLogIter::new(lines).take(1).collect();
+        assert_eq!(first.len(), 1);
+        // Only reads up to the header of the second entry
+        assert_eq!(lines_read, 5);
+    }
+
+    #[test]
+    fn test_iter_passes_through_source_errors() {
+        let lines = vec![
+            Ok("# User@Host: user[user] @  [127.0.0.1]"),
+            Ok("SELECT 1;"),
+            Err("read failed"),
+            Ok("# User@Host: user[user] @  [127.0.0.1]"),
+            Ok("SELECT 2;"),
+        ];
+        let results: Vec<_> = SlowLogIter::new(lines.into_iter())
+            .map(|r| r.map(|q| q.query))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                Err(Error::Read("read failed")),
+                Ok("SELECT 1;".to_string()),
+                Ok("SELECT 2;".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_is_fused() {
+        let mut iter = entries("SELECT 1;");
+        assert!(iter.next().is_some());
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_iter_yields_entry_errors() {
+        let data = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: 2024-01-01T12:01:00.000000Z
+# User@Host: user2[user2] @  [127.0.0.1]
+# Query_time: 0.5  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SELECT * FROM t WHERE name = 'unterminated
+# Time: 2024-01-01T12:02:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 2;
+";
+        let results: Vec<_> = entries(data).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[2].is_ok());
+
+        let Err(Error::Entry(err)) = &results[1] else {
+            panic!("expected an entry error, got {:?}", results[1]);
+        };
+        assert!(matches!(err.error, QueryError::ParseError(_)));
+        assert_eq!(err.query, "SELECT * FROM t WHERE name = 'unterminated");
+        assert_eq!(err.stats.user, "user2");
+        assert!((err.stats.query_time - 0.5).abs() < f64::EPSILON);
+        assert_eq!(err.line, 5);
+    }
+
+    #[test]
+    fn test_iter_entry_line_without_headers() {
+        let results: Vec<_> = entries("USE test;\nSELECT 'unterminated").collect();
+        let Err(Error::Entry(err)) = &results[0] else {
+            panic!("expected an entry error, got {:?}", results[0]);
+        };
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn test_iter_schema_from_use_and_header() {
+        let data = "use shop;
+# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM users;
+# Time: 2024-01-01T12:01:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM users;
+# Time: 2024-01-01T12:02:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Thread_id: 42  Schema: crm  Last_errno: 0  Killed: 0
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM users;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        let schemas: Vec<_> = queries.iter().map(|q| q.stats.schema.as_deref()).collect();
+        assert_eq!(schemas, [Some("shop"), Some("shop"), Some("crm")]);
+        assert_eq!(queries[2].query, "SELECT * FROM users;");
+        // Without the option the schema does not affect the fingerprint
+        assert_eq!(queries[0].fingerprint, queries[2].fingerprint);
+
+        let options = ParseOptions {
+            schema_in_fingerprint: true,
+            ..ParseOptions::default()
+        };
+        let queries: Vec<_> = entries(data)
+            .with_options(options)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(queries[0].fingerprint, queries[1].fingerprint);
+        assert_ne!(queries[0].fingerprint, queries[2].fingerprint);
+    }
+
+    #[test]
+    fn test_iter_start_time() {
+        let data = "# Time: 2021-07-01T00:00:02.500000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 2.5  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SET timestamp=1625097600;
+SELECT 1;
+# Time: 2021-07-01T00:00:10.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SET timestamp=1625097600;
+SELECT 2;
+# Time: 2021-07-01T00:00:11.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 3;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        let start = Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(queries[0].stats.start_time, Some(start));
+        assert!(queries[0].diagnostics.is_empty());
+
+        assert_eq!(queries[1].stats.start_time, Some(start));
+        assert_eq!(
+            queries[1].diagnostics,
+            [Diagnostic::TimeMismatch {
+                start,
+                end: Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 10).unwrap(),
+            }]
+        );
+
+        // The start time is per entry and does not carry over
+        assert_eq!(queries[2].stats.start_time, None);
+        assert!(queries[2].diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_iter_start_time_without_own_time() {
+        let data = "# Time: 2021-07-01T00:00:10.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: garbage
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SET timestamp=1704200000;
+SELECT 2;
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SET timestamp=1704200000;
+SELECT 3;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 3);
+        assert_eq!(
+            queries[1].diagnostics,
+            [Diagnostic::InvalidTimestamp {
+                line: 5,
+                value: "garbage".to_string(),
+            }]
+        );
+        // Without a `# Time:` header of its own there is no end time to check
+        assert!(queries[2].diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_iter_invalid_time_without_body() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: garbage
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+# Time: 2021-07-01T00:00:00.300000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 3;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        // The entry with the bad header has no body, so its diagnostic is
+        // dropped with it rather than attached to the next entry
+        assert!(queries[1].diagnostics.is_empty());
+        assert_eq!(queries[1].query, "SELECT 3;");
+    }
+
+    #[test]
+    fn test_iter_insert_ids() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SET last_insert_id=41,insert_id=42,timestamp=1625097600;
+INSERT INTO users (name) VALUES ('a'), ('b');
+# Time: 2021-07-01T00:00:00.200000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SET timestamp=1625097600;
+SET autocommit=0;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].stats.insert_id, Some(42));
+        assert_eq!(queries[0].stats.last_insert_id, Some(41));
+        assert!(queries[0].stats.start_time.is_some());
+
+        assert_eq!(queries[1].query, "SET autocommit=0;");
+        assert_eq!(queries[1].stats.insert_id, None);
+        assert_eq!(queries[1].stats.last_insert_id, None);
+    }
+
+    #[test]
+    fn test_iter_slow_extra() {
+        let data = "# Time: 2021-07-01T00:00:01.500000Z
+# User@Host: user[user] @  [127.0.0.1]  Id:     7
+# Query_time: 1.500000  Lock_time: 0.000100 Rows_sent: 1  Rows_examined: 5000 Thread_id: 7 Errno: 0 Killed: 0 Bytes_received: 40 Bytes_sent: 120 Read_first: 1 Read_last: 0 Read_key: 1 Read_next: 0 Read_prev: 0 Read_rnd: 0 Read_rnd_next: 5001 Sort_merge_passes: 0 Sort_range_count: 0 Sort_rows: 1 Sort_scan_count: 1 Created_tmp_disk_tables: 1 Created_tmp_tables: 1 Start: 2021-07-01T00:00:00.000000Z End: 2021-07-01T00:00:01.500000Z
+SET timestamp=1625097600;
+SELECT name FROM users ORDER BY RAND() LIMIT 1;
+# Time: 2021-07-01T00:00:02.000000Z
+# User@Host: user[user] @  [127.0.0.1]  Id:     7
+# Query_time: 0.100000  Lock_time: 0.000100 Rows_sent: 1  Rows_examined: 1
+SET timestamp=1625097602;
+SELECT 1;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        let stats = &queries[0].stats;
+        assert_eq!(stats.thread_id, Some(7));
+        assert_eq!(stats.errno, Some(0));
+        assert_eq!(stats.bytes_sent, Some(120));
+        assert_eq!(stats.read_rnd_next, Some(5001));
+        assert_eq!(stats.sort_scan_count, Some(1));
+        assert_eq!(stats.created_tmp_disk_tables, Some(1));
+        assert_eq!(
+            stats.start_time,
+            Some(Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(stats.end_time, Some(stats.time));
+        assert!(queries[0].diagnostics.is_empty());
+
+        let stats = &queries[1].stats;
+        assert_eq!(stats.thread_id, Some(7));
+        assert_eq!(stats.bytes_sent, None);
+        assert_eq!(stats.created_tmp_disk_tables, None);
+        assert_eq!(stats.end_time, None);
+        assert_eq!(
+            stats.start_time,
+            Some(Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 2).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_iter_header_only_entry() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]  Id:    10
+# Query_time: 9.000000  Lock_time: 1.000000 Rows_sent: 7  Rows_examined: 7 Bytes_sent: 500
+# administrator command: Quit;
+# Time: 2021-07-01T00:00:00.200000Z
+# User@Host: other[other] @  [127.0.0.2]
+# Query_time: 9.000000  Lock_time: 1.000000 Rows_sent: 7  Rows_examined: 7 Bytes_sent: 500
+# User@Host: app[app] @  [10.0.0.5]
+# Query_time: 0.100000  Lock_time: 0.000000 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].kind, QueryKind::Admin(AdminCommand::Quit));
+
+        // The header-only entry for `other` is dropped without leaking into
+        // the entry that follows it
+        let stats = &queries[1].stats;
+        assert_eq!(stats.user, "app");
+        assert_eq!(stats.thread_id, None);
+        assert_eq!(stats.bytes_sent, None);
+        assert!((stats.query_time - 0.1).abs() < f64::EPSILON);
+        assert_eq!(
+            stats.extra,
+            [
+                ("Query_time".to_string(), "0.100000".to_string()),
+                ("Lock_time".to_string(), "0.000000".to_string()),
+                ("Rows_sent".to_string(), "1".to_string()),
+                ("Rows_examined".to_string(), "1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_percona() {
+        let data = "# Time: 2021-07-01T00:00:00.500000Z
+# User@Host: shop[shop] @ localhost []  Id:    42
+# Schema: shop  Last_errno: 0  Killed: 0
+# Query_time: 0.500000  Lock_time: 0.000100  Rows_sent: 1  Rows_examined: 8000  Rows_affected: 0
+# Bytes_sent: 123  Tmp_tables: 1  Tmp_disk_tables: 1  Tmp_table_sizes: 16384
+# InnoDB_trx_id: 1A2B
+# QC_Hit: No  Full_scan: Yes  Full_join: No  Tmp_table: Yes  Tmp_table_on_disk: Yes
+# Filesort: Yes  Filesort_on_disk: No  Merge_passes: 0
+#   InnoDB_IO_r_ops: 2  InnoDB_IO_r_bytes: 32768  InnoDB_IO_r_wait: 0.001000
+#   InnoDB_rec_lock_wait: 0.000000  InnoDB_queue_wait: 0.000000
+#   InnoDB_pages_distinct: 9
+SET timestamp=1625097600;
+SELECT category, COUNT(*) FROM products GROUP BY category ORDER BY 2 DESC;
+# Time: 2021-07-01T00:00:01.000000Z
+# User@Host: shop[shop] @ localhost []  Id:    42
+# Schema: shop  Last_errno: 0  Killed: 0
+# Query_time: 0.100000  Lock_time: 0.000100  Rows_sent: 1  Rows_examined: 1  Rows_affected: 0
+# Bytes_sent: 60  Tmp_tables: 0  Tmp_disk_tables: 0  Tmp_table_sizes: 0
+# No InnoDB statistics available for this query
+SET timestamp=1625097601;
+SELECT 1;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].normaliser, Normaliser::Parser);
+        assert!(queries[0].query.starts_with("SELECT category"));
+
+        let stats = &queries[0].stats;
+        assert_eq!(stats.schema.as_deref(), Some("shop"));
+        assert_eq!(stats.thread_id, Some(42));
+        assert_eq!(stats.errno, Some(0));
+        assert_eq!(stats.killed, Some(0));
+        assert_eq!(stats.bytes_sent, Some(123));
+        let percona = stats.percona.as_ref().unwrap();
+        assert_eq!(percona.tmp_disk_tables, Some(1));
+        assert_eq!(percona.full_scan, Some(true));
+        assert_eq!(percona.qc_hit, Some(false));
+        assert_eq!(percona.filesort, Some(true));
+        assert_eq!(percona.innodb_trx_id.as_deref(), Some("1A2B"));
+        assert_eq!(percona.innodb_pages_distinct, Some(9));
+        assert_eq!(percona.innodb_io_r_wait, Some(0.001));
+
+        assert_eq!(queries[1].query, "SELECT 1;");
+        let percona = queries[1].stats.percona.as_ref().unwrap();
+        assert_eq!(percona.tmp_tables, Some(0));
+        assert_eq!(percona.innodb_pages_distinct, None);
+        assert_eq!(percona.full_scan, None);
+    }
+
+    #[test]
+    fn test_iter_mariadb() {
+        let data = "# Time: 210701  0:00:01
+# User@Host: shop[shop] @ localhost []
+# Thread_id: 42  Schema: shop  QC_hit: No
+# Query_time: 0.500000  Lock_time: 0.000100  Rows_sent: 1  Rows_examined: 1000
+# Rows_affected: 0  Bytes_sent: 94
+# Full_scan: Yes  Full_join: No  Tmp_table: No  Tmp_table_on_disk: No
+# Filesort: No  Filesort_on_disk: No  Merge_passes: 0  Priority_queue: No
+#
+# explain: id\tselect_type\ttable\ttype\tpossible_keys\tkey\tkey_len\tref\trows\tr_rows\tfiltered\tr_filtered\tExtra
+# explain: 1\tSIMPLE\torders\tALL\tNULL\tNULL\tNULL\tNULL\t1000\t1000.00\t100.00\t0.10\tUsing where
+#
+SET timestamp=1625097600;
+SELECT * FROM orders WHERE note LIKE '%gift%';
+";
+        let options = ParseOptions {
+            flavour: Flavour::MariaDb,
+            ..ParseOptions::default()
+        };
+        let queries: Vec<_> = entries(data)
+            .with_options(options)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(
+            queries[0].query,
+            "SELECT * FROM orders WHERE note LIKE '%gift%';"
+        );
+
+        let stats = &queries[0].stats;
+        assert_eq!(stats.thread_id, Some(42));
+        assert_eq!(stats.schema.as_deref(), Some("shop"));
+        assert_eq!(stats.bytes_sent, Some(94));
+        assert_eq!(stats.percona, None);
+        let mariadb = stats.mariadb.as_ref().unwrap();
+        assert_eq!(mariadb.qc_hit, Some(false));
+        assert_eq!(mariadb.full_scan, Some(true));
+        assert_eq!(mariadb.rows_affected, Some(0));
+        assert_eq!(mariadb.priority_queue, Some(false));
+        assert_eq!(mariadb.explain.len(), 1);
+        assert_eq!(mariadb.explain[0].table.as_deref(), Some("orders"));
+        assert_eq!(mariadb.explain[0].join_type.as_deref(), Some("ALL"));
+        assert_eq!(mariadb.explain[0].rows, Some(1000));
+        assert_eq!(mariadb.explain[0].extra.as_deref(), Some("Using where"));
+    }
+
+    #[test]
+    fn test_iter_extra_fields() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]  Id:     3
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0 Cpu_time: 0.09
+# Log_slow_rate_type: query  Log_slow_rate_limit: 10
+SELECT 1;
+# Time: 2021-07-01T00:00:00.200000Z
+# User@Host: user[user] @  [127.0.0.1]  Id:     3
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SELECT 2;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].query, "SELECT 1;");
+        let extra: Vec<_> = queries[0]
+            .stats
+            .extra
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            extra,
+            vec![
+                ("Query_time", "0.1"),
+                ("Lock_time", "0.0"),
+                ("Rows_sent", "0"),
+                ("Rows_examined", "0"),
+                ("Cpu_time", "0.09"),
+                ("Log_slow_rate_type", "query"),
+                ("Log_slow_rate_limit", "10"),
+            ]
+        );
+        assert_eq!(queries[1].stats.extra.len(), 4);
+    }
+
+    #[test]
+    fn test_iter_metadata_never_in_query() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.5  Lock_time: 0.1 Rows_sent: 3
+# Some future header without fields
+#
+SELECT * FROM users;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].query, "SELECT * FROM users;");
+        assert_eq!(queries[0].normaliser, Normaliser::Parser);
+        assert!((queries[0].stats.query_time - 0.5).abs() < f64::EPSILON);
+        assert!((queries[0].stats.lock_time - 0.1).abs() < f64::EPSILON);
+        assert_eq!(queries[0].stats.rows_sent, 3);
+        assert_eq!(queries[0].stats.rows_examined, 0);
+    }
+
+    #[test]
+    fn test_iter_keeps_body_lines() {
+        let data = "/usr/sbin/mysqld, Version: 8.0.26 (MySQL Community Server - GPL). started with:
+Tcp port: 3306  Unix socket: /var/run/mysqld/mysqld.sock
+Time                 Id Command    Argument
+# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+use shop;
+SET timestamp=1625097600;
+/* caller: OrderRepository::recent */
+SELECT * FROM orders
+USE INDEX (idx_created)
+WHERE created_at
+timestamp > NOW();
+# Time: 2021-07-01T00:00:00.200000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+UPDATE orders
+SET timestamp=1625097600;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(
+            queries[0].query,
+            "/* caller: OrderRepository::recent */ SELECT * FROM orders USE INDEX (idx_created) \
+             WHERE created_at timestamp > NOW();"
+        );
+        assert_eq!(queries[0].stats.schema.as_deref(), Some("shop"));
+        assert_eq!(queries[1].query, "UPDATE orders SET timestamp=1625097600;");
+        assert_eq!(queries[1].stats.start_time, None);
+    }
+
+    #[test]
+    fn test_iter_multibyte_body_lines() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SELECT name,
+日本語 AS x FROM t;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].query, "SELECT name, 日本語 AS x FROM t;");
+    }
+
+    #[test]
+    fn test_iter_server_segments() {
+        let data = "/usr/sbin/mysqld, Version: 8.0.26 (MySQL Community Server - GPL). started with:
+Tcp port: 3306  Unix socket: /var/run/mysqld/mysqld.sock
+Time                 Id Command    Argument
+# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SELECT 1;
+/usr/sbin/mysqld, Version: 8.0.27 (MySQL Community Server - GPL). started with:
+Tcp port: 3307  Unix socket: /var/run/mysqld/mysqld.sock
+Time                 Id Command    Argument
+# Time: 2021-07-02T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SELECT 2;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+        let first = queries[0].server.as_ref().unwrap();
+        assert_eq!(first.binary, "/usr/sbin/mysqld");
+        assert_eq!(first.version, "8.0.26");
+        assert_eq!(first.version_comment, "MySQL Community Server - GPL");
+        assert_eq!(first.port, Some(3306));
+        assert_eq!(first.socket.as_deref(), Some("/var/run/mysqld/mysqld.sock"));
+        let second = queries[1].server.as_ref().unwrap();
+        assert_eq!(second.version, "8.0.27");
+        assert_eq!(second.port, Some(3307));
+
+        assert_eq!(entries("SELECT 1;").next().unwrap().unwrap().server, None);
+    }
+
+    #[test]
+    fn test_iter_auto_flavour() {
+        let data = "/usr/sbin/mariadbd, Version: 10.6.5-MariaDB-log (MariaDB Server). started with:
+Tcp port: 3306  Unix socket: /run/mysqld/mysqld.sock
+Time                 Id Command    Argument
+# Time: 211201 12:00:00
+# User@Host: shop[shop] @ localhost []
+# Thread_id: 42  Schema: shop  QC_hit: No
+# Query_time: 0.500000  Lock_time: 0.000100  Rows_sent: 1  Rows_examined: 1000
+# Full_scan: Yes  Full_join: No  Tmp_table: No  Tmp_table_on_disk: No
+# explain: id\tselect_type\ttable\ttype\tpossible_keys\tkey\tkey_len\tref\trows\tr_rows\tfiltered\tr_filtered\tExtra
+# explain: 1\tSIMPLE\torders\tALL\tNULL\tNULL\tNULL\tNULL\t1000\t1000.00\t100.00\t0.10\tUsing where
+SELECT * FROM orders;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        let mariadb = queries[0].stats.mariadb.as_ref().unwrap();
+        assert_eq!(mariadb.qc_hit, Some(false));
+        assert_eq!(mariadb.full_scan, Some(true));
+        assert_eq!(mariadb.explain.len(), 1);
+        assert_eq!(queries[0].stats.percona, None);
+
+        let options = ParseOptions {
+            flavour: Flavour::MySql,
+            ..ParseOptions::default()
+        };
+        let queries: Vec<_> = entries(data)
+            .with_options(options)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(queries[0].stats.mariadb, None);
+        assert!(queries[0]
+            .stats
+            .extra
+            .iter()
+            .all(|(key, _)| key != "explain"));
+    }
+
+    #[test]
+    fn test_iter_admin_commands() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: app[app] @  [10.0.0.5]  Id:    12
+# Query_time: 2.000000  Lock_time: 0.000000 Rows_sent: 0  Rows_examined: 0
+SET timestamp=1625097598;
+# administrator command: Prepare;
+# Time: 2021-07-01T00:00:00.200000Z
+# User@Host: app[app] @  [10.0.0.5]  Id:    12
+# Query_time: 0.100000  Lock_time: 0.000000 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: 2021-07-01T00:00:00.300000Z
+# User@Host: app[app] @  [10.0.0.5]  Id:    12
+# Query_time: 1.000000  Lock_time: 0.000000 Rows_sent: 0  Rows_examined: 0
+# administrator command: Close stmt;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 3);
+
+        assert_eq!(queries[0].kind, QueryKind::Admin(AdminCommand::Prepare));
+        assert_eq!(queries[0].query, "# administrator command: Prepare;");
+        assert_eq!(queries[0].formatted, "administrator command: Prepare");
+        assert_eq!(
+            queries[0].fingerprint,
+            sql::fingerprint_query("administrator command: Prepare")
+        );
+        assert_eq!(queries[0].normaliser, Normaliser::Command);
+        assert_eq!(queries[0].stats.thread_id, Some(12));
+        assert!((queries[0].stats.query_time - 2.0).abs() < f64::EPSILON);
+
+        assert_eq!(queries[1].kind, QueryKind::Sql);
+        assert_eq!(queries[1].query, "SELECT 1;");
+
+        assert_eq!(queries[2].kind, QueryKind::Admin(AdminCommand::CloseStmt));
+        assert_eq!(queries[2].formatted, "administrator command: Close stmt");
+    }
+
+    #[test]
+    fn test_iter_admin_command_followed_by_sql() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: app[app] @  [10.0.0.5]  Id:    12
+# Query_time: 0.100000  Lock_time: 0.000000 Rows_sent: 0  Rows_examined: 0
+# administrator command: Quit;
+
+use shop;
+SET timestamp=1625097600;
+SELECT 1;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        assert_eq!(queries.len(), 2);
+
+        assert_eq!(queries[0].kind, QueryKind::Admin(AdminCommand::Quit));
+        assert_eq!(queries[0].query, "# administrator command: Quit;");
+        assert_eq!(queries[0].stats.schema, None);
+        assert_eq!(queries[0].stats.start_time, None);
+
+        assert_eq!(queries[1].kind, QueryKind::Sql);
+        assert_eq!(queries[1].query, "SELECT 1;");
+        assert_eq!(queries[1].formatted, "SELECT ?");
+        assert_eq!(queries[1].normaliser, Normaliser::Parser);
+        assert_eq!(queries[1].stats.schema.as_deref(), Some("shop"));
+        assert_eq!(
+            queries[1].stats.start_time,
+            Some(Utc.with_ymd_and_hms(2021, 7, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_iter_multiple_statements() {
+        let data = "# Time: 2021-07-01T00:00:00.100000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 1
+BEGIN; UPDATE accounts SET balance = balance - 10 WHERE id = 1;
+COMMIT;
+# Time: 2021-07-01T00:00:00.200000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.1  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 1
+UPDATE accounts SET balance = balance - 25 WHERE id = 7;
+";
+        let queries: Vec<_> = entries(data).map(Result::unwrap).collect();
+        let update = "UPDATE accounts SET balance = balance - ? WHERE id = ?";
+
+        let formatted: Vec<_> = queries[0]
+            .statements
+            .iter()
+            .map(|statement| statement.formatted.as_str())
+            .collect();
+        assert_eq!(formatted, vec!["BEGIN", update, "COMMIT"]);
+        assert_eq!(queries[0].formatted, format!("BEGIN; {update}; COMMIT"));
+        assert_eq!(
+            queries[0].fingerprint,
+            sql::fingerprint_query(&queries[0].formatted)
+        );
+
+        assert_eq!(queries[1].statements.len(), 1);
+        assert_eq!(queries[1].formatted, update);
+        assert_eq!(queries[1].statements[0].fingerprint, queries[1].fingerprint);
+        assert_eq!(queries[0].statements[1].fingerprint, queries[1].fingerprint);
+    }
+}
@@ -0,0 +1,248 @@
+use super::QueryError;
+use sqlparser::dialect::MySqlDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+/// Returns true for tokens that carry a literal value.
+fn is_literal(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(..)
+            | Token::SingleQuotedString(_)
+            | Token::DoubleQuotedString(_)
+            | Token::TripleSingleQuotedString(_)
+            | Token::TripleDoubleQuotedString(_)
+            | Token::DollarQuotedString(_)
+            | Token::SingleQuotedByteStringLiteral(_)
+            | Token::DoubleQuotedByteStringLiteral(_)
+            | Token::TripleSingleQuotedByteStringLiteral(_)
+            | Token::TripleDoubleQuotedByteStringLiteral(_)
+            | Token::SingleQuotedRawStringLiteral(_)
+            | Token::DoubleQuotedRawStringLiteral(_)
+            | Token::TripleSingleQuotedRawStringLiteral(_)
+            | Token::TripleDoubleQuotedRawStringLiteral(_)
+            | Token::NationalStringLiteral(_)
+            | Token::QuoteDelimitedStringLiteral(_)
+            | Token::NationalQuoteDelimitedStringLiteral(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::UnicodeStringLiteral(_)
+            | Token::HexStringLiteral(_)
+    )
+}
+
+/// Returns true for tokens that can precede a unary sign, such as
+/// comparison and arithmetic operators.
+fn is_operator(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::DoubleEq
+            | Token::Eq
+            | Token::Neq
+            | Token::Lt
+            | Token::Gt
+            | Token::LtEq
+            | Token::GtEq
+            | Token::Spaceship
+            | Token::Plus
+            | Token::Minus
+            | Token::Mul
+            | Token::Div
+            | Token::Mod
+            | Token::StringConcat
+            | Token::Assignment
+            | Token::Ampersand
+            | Token::Pipe
+            | Token::Caret
+            | Token::ShiftLeft
+            | Token::ShiftRight
+    )
+}
+
+fn placeholder() -> Token {
+    Token::Placeholder("?".to_string())
+}
+
+/// Finds the opening parenthesis of an `IN (?, ?, ...)` list ending at the
+/// end of `tokens`, returning the index just past it.
+fn in_list_start(tokens: &[Token]) -> Option<usize> {
+    let paren = tokens.iter().rposition(|token| {
+        !matches!(
+            token,
+            Token::Placeholder(_) | Token::Comma | Token::Whitespace(_)
+        )
+    })?;
+    if tokens[paren] != Token::LParen || paren + 1 == tokens.len() {
+        return None;
+    }
+
+    let ([.., ref keyword, Token::Whitespace(_)] | [.., ref keyword]) = tokens[..paren] else {
+        return None;
+    };
+    match keyword {
+        Token::Word(word) if word.keyword == Keyword::IN && word.quote_style.is_none() => {
+            Some(paren + 1)
+        }
+        _ => None,
+    }
+}
+
+/// Finds a unary `-` or `+` at the end of `tokens` that belongs to the number
+/// that follows, returning its index. A sign is unary when it follows an
+/// operator, `(` or `,`; otherwise it is a binary operator, as in `a - 1`.
+fn sign_start(tokens: &[Token]) -> Option<usize> {
+    let is_token = |token: &&Token| !matches!(token, Token::Whitespace(_));
+    let sign = tokens.iter().rposition(|token| is_token(&token))?;
+    if !matches!(tokens[sign], Token::Minus | Token::Plus) {
+        return None;
+    }
+
+    match tokens[..sign].iter().rfind(is_token)? {
+        Token::LParen | Token::Comma => Some(sign),
+        token if is_operator(token) => Some(sign),
+        _ => None,
+    }
+}
+
+/// Normalises a SQL query without parsing it.
+///
+/// Used as a fallback for statements that [`crate::sql::format_statements`] cannot
+/// parse, such as stored-procedure bodies, `LOAD DATA` or vendor syntax. The
+/// query is tokenized and then:
+///
+/// - string, numeric and hex literals are replaced with `?`, taking a unary
+///   sign with them, so `-1` and `1` normalise alike
+/// - `IN (...)` lists of literals are collapsed to `IN (?)`
+/// - comments are dropped and whitespace is collapsed to single spaces
+/// - unquoted words are lowercased, so keyword and identifier case does not
+///   affect the fingerprint
+///
+/// Returns `QueryError::ParseError` if the query cannot be tokenized.
+/// Returns `QueryError::InvalidQuery` if no tokens remain.
+pub(crate) fn normalise_query(input: &str) -> Result<String, QueryError> {
+    let tokens = Tokenizer::new(&MySqlDialect {}, input)
+        .with_unescape(false)
+        .tokenize()
+        .map_err(|e| QueryError::ParseError(e.to_string()))?;
+
+    let mut normalised: Vec<Token> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let token = match token {
+            Token::Whitespace(_) => {
+                if !matches!(normalised.last(), None | Some(Token::Whitespace(_))) {
+                    normalised.push(Token::Whitespace(Whitespace::Space));
+                }
+                continue;
+            }
+            Token::Word(mut word) if word.quote_style.is_none() => {
+                word.value = word.value.to_lowercase();
+                Token::Word(word)
+            }
+            Token::RParen => {
+                if let Some(start) = in_list_start(&normalised) {
+                    normalised.truncate(start);
+                    normalised.push(placeholder());
+                }
+                Token::RParen
+            }
+            Token::Number(..) => {
+                if let Some(start) = sign_start(&normalised) {
+                    normalised.truncate(start);
+                }
+                placeholder()
+            }
+            token if is_literal(&token) => placeholder(),
+            token => token,
+        };
+        normalised.push(token);
+    }
+
+    while matches!(
+        normalised.last(),
+        Some(Token::Whitespace(_) | Token::SemiColon)
+    ) {
+        normalised.pop();
+    }
+
+    if normalised.is_empty() {
+        return Err(QueryError::InvalidQuery);
+    }
+
+    Ok(normalised.iter().map(ToString::to_string).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literals() {
+        let input = "SELECT * FROM users WHERE name = 'test' AND age > 18 AND flags = X'0F'";
+        let expected = "select * from users where name = ? and age > ? and flags = ?";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_in_list() {
+        let input = "DELETE FROM t WHERE id IN (1, 2, 3) AND name NOT IN ('a','b')";
+        let expected = "delete from t where id in (?) and name not in (?)";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_signed_numbers() {
+        let input = "SELECT a - 1 FROM t WHERE x = -1 AND y IN (-1, +2, 3) AND z > - 5";
+        let expected = "select a - ? from t where x = ? and y in (?) and z > ?";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+        assert_eq!(
+            normalise_query("CALL p(-1, 2 - -3)").unwrap(),
+            "call p(?, ? - ?)"
+        );
+    }
+
+    #[test]
+    fn test_in_subquery_kept() {
+        let input = "SELECT * FROM t WHERE id IN (SELECT id FROM u WHERE x = 1)";
+        let expected = "select * from t where id in (select id from u where x = ?)";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_whitespace_and_comments() {
+        let input = "SELECT  /*+ MAX_EXECUTION_TIME(1000) */ id\n\tFROM t  -- trailing\n;";
+        let expected = "select id from t";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_load_data() {
+        let input =
+            "LOAD DATA INFILE '/tmp/data.csv' INTO TABLE `imports` FIELDS TERMINATED BY ','";
+        let expected = "load data infile ? into table `imports` fields terminated by ?";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_quoted_identifiers_keep_case() {
+        let input = "SELECT `UserId` FROM Users WHERE `Name` = \"bob\"";
+        let expected = "select `UserId` from users where `Name` = ?";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_function_arguments() {
+        let input = "CALL refresh_totals(42, 'daily')";
+        let expected = "call refresh_totals(?, ?)";
+        assert_eq!(normalise_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let result = normalise_query("SELECT 'unterminated");
+        assert!(matches!(result, Err(QueryError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_empty_query() {
+        assert_eq!(normalise_query(" ; "), Err(QueryError::InvalidQuery));
+    }
+}
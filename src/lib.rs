@@ -37,6 +37,21 @@
 //!     println!("Rows examined: {}", query.stats.rows_examined);
 //! });
 //! ```
+//!
+//! ## Iterating over entries
+//!
+//! ```
+//! use slowlog::SlowLogIter;
+//!
+//! let data = "# Query_time: 2.5  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+//! SELECT * FROM users WHERE id = 1;";
+//!
+//! let lines = data.lines().map(Ok::<_, std::convert::Infallible>);
+//! for query in SlowLogIter::new(lines).take(10) {
+//!     let query = query.unwrap();
+//!     println!("{:.2}s {}", query.stats.query_time, query.formatted);
+//! }
+//! ```
 //! # Query Normalisation
 //!
 //! Queries are normalised by replacing all literal values with `?` placeholders:
@@ -48,9 +63,13 @@
 //! | `INSERT INTO users (name, age) VALUES ('Alice', 25)` | `INSERT INTO users (name, age) VALUES (?, ?)` |
 
 mod helpers;
+mod iter;
+mod lexical;
 mod sql;
 
-use chrono::{DateTime, TimeZone, Utc};
+pub use iter::SlowLogIter;
+
+use chrono::{DateTime, Utc};
 use sqlparser::parser::ParserError;
 
 #[cfg(feature = "readers")]
@@ -64,22 +83,224 @@ use std::io::{self, BufRead, BufReader};
 ///
 /// # Fields
 ///
-/// * `user` - Database user who executed the query
-/// * `host` - Client host from which the query was executed
-/// * `time` - Timestamp when the query was executed
+/// * `user` - Effective database user whose privileges the query ran with
+/// * `auth_user` - User that authenticated; differs from `user` for proxy users
+/// * `host` - Client IP address from the brackets, empty for local socket
+///   connections
+/// * `hostname` - Client hostname, empty when the server did not resolve it
+/// * `thread_id` - Connection (thread) id of the session, from the `Id:` field
+/// * `schema` - Current database, from `USE` lines or `Schema:` header fields
+/// * `time` - Time the entry was written, from the `# Time:` header, which is
+///   when the query finished
+/// * `start_time` - Time the query started, from the `SET timestamp` line
+/// * `insert_id` - First auto-increment value the statement used, from `SET insert_id`
+/// * `last_insert_id` - Value of `LAST_INSERT_ID()` the statement saw, from
+///   `SET last_insert_id`
 /// * `query_time` - Total query execution time in seconds
 /// * `lock_time` - Time spent waiting for locks in seconds
 /// * `rows_sent` - Number of rows returned by the query
 /// * `rows_examined` - Number of rows examined during query execution
-#[derive(Debug, Clone, PartialEq)]
+/// * `end_time` - Time the query finished, from the `End:` field
+///
+/// The remaining fields are only written with `log_slow_extra=ON` (MySQL
+/// 8.0.14 and later) and are `None` otherwise:
+///
+/// * `errno` - Error number the statement finished with, 0 on success
+/// * `killed` - Error number of the kill if the statement was killed, else 0
+/// * `bytes_received` / `bytes_sent` - Network traffic for the statement
+/// * `read_first`, `read_last`, `read_key`, `read_next`, `read_prev`,
+///   `read_rnd`, `read_rnd_next` - Storage engine handler calls, matching the
+///   `Handler_read_*` status variables
+/// * `sort_merge_passes`, `sort_range_count`, `sort_rows`, `sort_scan_count` -
+///   Sorting work, matching the `Sort_*` status variables
+/// * `created_tmp_disk_tables` / `created_tmp_tables` - Internal temporary
+///   tables created, on disk and in total
+///
+/// `log_slow_extra` also writes `Thread_id` and `Start`, which fill
+/// `thread_id` and `start_time`. Percona Server's `Killed`, `Last_errno` and
+/// `Bytes_sent` header fields fill `killed`, `errno` and `bytes_sent`.
+///
+/// * `extra` - Every `Key: value` field of the entry's `#` header lines other
+///   than `# Time:` and `# User@Host:`, in log order and as written, including
+///   fields this crate does not recognise
+/// * `percona` - Percona Server `log_slow_verbosity` fields, if any were logged
+/// * `mariadb` - MariaDB extended fields and `EXPLAIN` output, parsed when
+///   the log is read as MariaDB (see [`Flavour`])
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct QueryStats {
     pub user: String,
+    pub auth_user: String,
     pub host: String,
+    pub hostname: String,
+    pub thread_id: Option<u64>,
+    pub schema: Option<String>,
     pub time: DateTime<Utc>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub insert_id: Option<u64>,
+    pub last_insert_id: Option<u64>,
     pub rows_examined: u64,
     pub rows_sent: u64,
     pub query_time: f64,
     pub lock_time: f64,
+    pub end_time: Option<DateTime<Utc>>,
+    pub errno: Option<u32>,
+    pub killed: Option<u32>,
+    pub bytes_received: Option<u64>,
+    pub bytes_sent: Option<u64>,
+    pub read_first: Option<u64>,
+    pub read_last: Option<u64>,
+    pub read_key: Option<u64>,
+    pub read_next: Option<u64>,
+    pub read_prev: Option<u64>,
+    pub read_rnd: Option<u64>,
+    pub read_rnd_next: Option<u64>,
+    pub sort_merge_passes: Option<u64>,
+    pub sort_range_count: Option<u64>,
+    pub sort_rows: Option<u64>,
+    pub sort_scan_count: Option<u64>,
+    pub created_tmp_disk_tables: Option<u64>,
+    pub created_tmp_tables: Option<u64>,
+    pub extra: Vec<(String, String)>,
+    pub percona: Option<PerconaStats>,
+    pub mariadb: Option<MariaDbStats>,
+}
+
+/// Extended statistics written by Percona Server's `log_slow_verbosity`.
+///
+/// Each field is `None` unless its header field was present, as the fields
+/// logged depend on the verbosity settings and storage engine.
+///
+/// # Fields
+///
+/// * `rows_affected` - Rows changed by the statement
+/// * `tmp_tables` / `tmp_disk_tables` - Implicit temporary tables created, in
+///   total and on disk
+/// * `tmp_table_sizes` - Total size in bytes of the temporary tables
+/// * `qc_hit` - Whether the result came from the query cache
+/// * `full_scan` / `full_join` - Whether a full table scan or a join without
+///   indexes was performed
+/// * `tmp_table` / `tmp_table_on_disk` - Whether a temporary table was used,
+///   and whether it went to disk
+/// * `filesort` / `filesort_on_disk` - Whether a filesort was used, and
+///   whether it went to disk
+/// * `merge_passes` - Number of sort merge passes
+/// * `innodb_trx_id` - InnoDB transaction id, as written (hexadecimal)
+/// * `innodb_io_r_ops` / `innodb_io_r_bytes` - Page read operations and bytes
+/// * `innodb_io_r_wait` - Seconds spent waiting for page reads
+/// * `innodb_rec_lock_wait` - Seconds spent waiting for row locks
+/// * `innodb_queue_wait` - Seconds spent waiting to enter InnoDB
+/// * `innodb_pages_distinct` - Approximate number of distinct pages accessed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PerconaStats {
+    pub rows_affected: Option<u64>,
+    pub tmp_tables: Option<u64>,
+    pub tmp_disk_tables: Option<u64>,
+    pub tmp_table_sizes: Option<u64>,
+    pub qc_hit: Option<bool>,
+    pub full_scan: Option<bool>,
+    pub full_join: Option<bool>,
+    pub tmp_table: Option<bool>,
+    pub tmp_table_on_disk: Option<bool>,
+    pub filesort: Option<bool>,
+    pub filesort_on_disk: Option<bool>,
+    pub merge_passes: Option<u64>,
+    pub innodb_trx_id: Option<String>,
+    pub innodb_io_r_ops: Option<u64>,
+    pub innodb_io_r_bytes: Option<u64>,
+    pub innodb_io_r_wait: Option<f64>,
+    pub innodb_rec_lock_wait: Option<f64>,
+    pub innodb_queue_wait: Option<f64>,
+    pub innodb_pages_distinct: Option<u64>,
+}
+
+/// Extended statistics written by MariaDB's `log_slow_verbosity`.
+///
+/// Only filled when parsing with [`Flavour::MariaDb`], or with
+/// [`Flavour::Auto`] after a MariaDB startup banner. Each field is `None`
+/// unless its header field was present.
+///
+/// # Fields
+///
+/// * `rows_affected` - Rows changed by the statement
+/// * `qc_hit` - Whether the result came from the query cache
+/// * `full_scan` / `full_join` - Whether a full table scan or a join without
+///   indexes was performed
+/// * `tmp_table` / `tmp_table_on_disk` - Whether a temporary table was used,
+///   and whether it went to disk
+/// * `filesort` / `filesort_on_disk` - Whether a filesort was used, and
+///   whether it went to disk
+/// * `merge_passes` - Number of sort merge passes
+/// * `priority_queue` - Whether the sort used a priority queue for `LIMIT`
+/// * `tmp_tables` / `tmp_disk_tables` - Temporary tables created, in total and
+///   on disk
+/// * `pages_accessed`, `pages_read`, `pages_prefetched`, `pages_updated` -
+///   Storage engine page activity (`log_slow_verbosity=engine`)
+/// * `undo_records_added` / `old_rows_read` - Undo records written and old row
+///   versions read
+/// * `pages_read_time` / `engine_time` - Time spent reading pages and in the
+///   storage engine, as logged
+/// * `explain` - `EXPLAIN` rows from `# explain:` lines
+///   (`log_slow_verbosity=explain`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MariaDbStats {
+    pub rows_affected: Option<u64>,
+    pub qc_hit: Option<bool>,
+    pub full_scan: Option<bool>,
+    pub full_join: Option<bool>,
+    pub tmp_table: Option<bool>,
+    pub tmp_table_on_disk: Option<bool>,
+    pub filesort: Option<bool>,
+    pub filesort_on_disk: Option<bool>,
+    pub merge_passes: Option<u64>,
+    pub priority_queue: Option<bool>,
+    pub tmp_tables: Option<u64>,
+    pub tmp_disk_tables: Option<u64>,
+    pub pages_accessed: Option<u64>,
+    pub pages_read: Option<u64>,
+    pub pages_prefetched: Option<u64>,
+    pub pages_updated: Option<u64>,
+    pub undo_records_added: Option<u64>,
+    pub old_rows_read: Option<u64>,
+    pub pages_read_time: Option<f64>,
+    pub engine_time: Option<f64>,
+    pub explain: Vec<ExplainRow>,
+}
+
+/// One row of the `EXPLAIN` output MariaDB logs with each slow query.
+///
+/// Columns are matched by the header row, so columns the server did not write
+/// and `NULL` values are `None`.
+///
+/// # Fields
+///
+/// * `id` - `SELECT` identifier
+/// * `select_type` - Kind of `SELECT`, e.g. `SIMPLE` or `SUBQUERY`
+/// * `table` - Table the row refers to
+/// * `join_type` - Access method, the `type` column, e.g. `ALL` or `ref`
+/// * `possible_keys` - Indexes the optimizer could choose from
+/// * `key` - Index actually chosen
+/// * `key_len` - Length of the chosen key
+/// * `reference` - Columns or constants compared to the index, the `ref` column
+/// * `rows` - Estimated rows examined
+/// * `r_rows` - Actual rows examined, from `ANALYZE`
+/// * `filtered` - Estimated percentage of rows filtered by the condition
+/// * `r_filtered` - Actual percentage of rows filtered, from `ANALYZE`
+/// * `extra` - Additional information, e.g. `Using where`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExplainRow {
+    pub id: Option<u64>,
+    pub select_type: Option<String>,
+    pub table: Option<String>,
+    pub join_type: Option<String>,
+    pub possible_keys: Option<String>,
+    pub key: Option<String>,
+    pub key_len: Option<String>,
+    pub reference: Option<String>,
+    pub rows: Option<u64>,
+    pub r_rows: Option<f64>,
+    pub filtered: Option<f64>,
+    pub r_filtered: Option<f64>,
+    pub extra: Option<String>,
 }
 
 /// A parsed slow log query entry.
@@ -90,9 +311,21 @@ pub struct QueryStats {
 /// # Fields
 ///
 /// * `query` - The original SQL query text as it appeared in the log
-/// * `formatted` - Normalised query with all literal values replaced by `?` placeholders
-/// * `fingerprint` - SHA1 hash of the formatted query for grouping similar queries
+/// * `formatted` - Normalised query with all literal values replaced by `?` placeholders.
+///   For entries holding several statements, each statement's formatted text
+///   joined with `"; "`
+/// * `fingerprint` - SHA1 hash of `formatted`, for grouping similar queries.
+///   A multi-statement entry therefore groups with entries running the same
+///   sequence of statements; use `statements` to group statement by statement
+/// * `statements` - Formatted text and fingerprint of each statement in the
+///   entry, in order. Entries normalised by [`Normaliser::Lexical`] are not split
+///   and hold a single statement
+/// * `normaliser` - Which normaliser produced `formatted`
+/// * `kind` - Whether the entry is an SQL statement or an administrator command
 /// * `stats` - Execution statistics and metadata for this query
+/// * `diagnostics` - Problems found in the entry's header lines
+/// * `server` - Server run the entry belongs to, from the most recent startup
+///   banner, or `None` if the log has not shown one yet
 ///
 /// # Examples
 ///
@@ -112,7 +345,187 @@ pub struct Query {
     pub query: String,
     pub formatted: String,
     pub fingerprint: String,
+    pub statements: Vec<Statement>,
+    pub normaliser: Normaliser,
+    pub kind: QueryKind,
     pub stats: QueryStats,
+    pub diagnostics: Vec<Diagnostic>,
+    pub server: Option<ServerInfo>,
+}
+
+/// One normalised statement of a slow log entry.
+///
+/// # Fields
+///
+/// * `formatted` - Normalised statement with literal values replaced by `?`
+/// * `fingerprint` - SHA1 hash of `formatted`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub formatted: String,
+    pub fingerprint: String,
+}
+
+/// Details of a server run, from the banner written when the server starts and
+/// opens the slow log.
+///
+/// A new banner marks a restart (or a log flush), so entries can be split per
+/// server run by comparing their `server` values.
+///
+/// # Fields
+///
+/// * `binary` - Path of the server binary, e.g. `/usr/sbin/mysqld`
+/// * `version` - Server version, e.g. `8.0.26` or `10.6.5-MariaDB-log`
+/// * `version_comment` - Distribution description from the banner, e.g.
+///   `MySQL Community Server - GPL`
+/// * `port` - TCP port, 0 when networking is disabled
+/// * `socket` - Unix socket path, or named pipe on Windows
+///
+/// # Examples
+///
+/// ```
+/// use slowlog::SlowLogIter;
+///
+/// let data = "/usr/sbin/mariadbd, Version: 10.6.5-MariaDB-log (MariaDB Server). started with:
+/// Tcp port: 3306  Unix socket: /run/mysqld/mysqld.sock
+/// Time                 Id Command    Argument
+/// ## Time: 211201 12:00:00
+/// ## User@Host: app[app] @ localhost []
+/// ## Query_time: 1.5  Lock_time: 0.1  Rows_sent: 1  Rows_examined: 100
+/// SELECT 1;";
+///
+/// let query = SlowLogIter::new(data.lines().map(Ok::<_, std::convert::Infallible>))
+///     .next()
+///     .unwrap()
+///     .unwrap();
+/// let server = query.server.unwrap();
+/// assert_eq!(server.version, "10.6.5-MariaDB-log");
+/// assert_eq!(server.port, Some(3306));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerInfo {
+    pub binary: String,
+    pub version: String,
+    pub version_comment: String,
+    pub port: Option<u16>,
+    pub socket: Option<String>,
+}
+
+impl ServerInfo {
+    /// Returns true if the banner came from a MariaDB server.
+    #[must_use]
+    pub fn is_mariadb(&self) -> bool {
+        self.version.contains("MariaDB") || self.version_comment.contains("MariaDB")
+    }
+}
+
+/// The normaliser that produced a query's formatted text.
+///
+/// Statements that the SQL parser cannot handle, such as stored-procedure
+/// bodies, `LOAD DATA` or vendor-specific syntax, fall back to a token-based
+/// normaliser. Its output is lowercased and is not comparable with parser
+/// output, so the same statement normalised both ways has two fingerprints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normaliser {
+    /// The query was parsed and literals were replaced in the syntax tree.
+    Parser,
+
+    /// The query could not be parsed. Literals were replaced token by token,
+    /// `IN` lists collapsed and unquoted words lowercased.
+    Lexical,
+
+    /// The entry is an administrator command rather than SQL. The formatted
+    /// text is `administrator command: <name>`, as pt-query-digest writes it.
+    Command,
+}
+
+/// What kind of work a slow log entry records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryKind {
+    /// An SQL statement.
+    Sql,
+
+    /// A protocol command logged as `# administrator command: <name>;`,
+    /// such as `Quit` or `Prepare`.
+    Admin(AdminCommand),
+}
+
+/// A protocol command the server logs as `# administrator command:`.
+///
+/// Displays as the name the server writes, e.g. `Close stmt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Quit,
+    InitDb,
+    FieldList,
+    Statistics,
+    Ping,
+    ChangeUser,
+    BinlogDump,
+    BinlogDumpGtid,
+    RegisterReplica,
+    Prepare,
+    Execute,
+    LongData,
+    CloseStmt,
+    ResetStmt,
+    SetOption,
+    Fetch,
+    ResetConnection,
+    /// Any other command, with its name as written
+    Other(String),
+}
+
+impl AdminCommand {
+    /// Looks up a command by the name the server writes for it.
+    #[must_use]
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Quit" => Self::Quit,
+            "Init DB" => Self::InitDb,
+            "Field List" => Self::FieldList,
+            "Statistics" => Self::Statistics,
+            "Ping" => Self::Ping,
+            "Change user" => Self::ChangeUser,
+            "Binlog Dump" => Self::BinlogDump,
+            "Binlog Dump GTID" => Self::BinlogDumpGtid,
+            "Register Replica" | "Register Slave" => Self::RegisterReplica,
+            "Prepare" => Self::Prepare,
+            "Execute" => Self::Execute,
+            "Long Data" => Self::LongData,
+            "Close stmt" => Self::CloseStmt,
+            "Reset stmt" => Self::ResetStmt,
+            "Set option" => Self::SetOption,
+            "Fetch" => Self::Fetch,
+            "Reset Connection" => Self::ResetConnection,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl core::fmt::Display for AdminCommand {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Self::Quit => "Quit",
+            Self::InitDb => "Init DB",
+            Self::FieldList => "Field List",
+            Self::Statistics => "Statistics",
+            Self::Ping => "Ping",
+            Self::ChangeUser => "Change user",
+            Self::BinlogDump => "Binlog Dump",
+            Self::BinlogDumpGtid => "Binlog Dump GTID",
+            Self::RegisterReplica => "Register Replica",
+            Self::Prepare => "Prepare",
+            Self::Execute => "Execute",
+            Self::LongData => "Long Data",
+            Self::CloseStmt => "Close stmt",
+            Self::ResetStmt => "Reset stmt",
+            Self::SetOption => "Set option",
+            Self::Fetch => "Fetch",
+            Self::ResetConnection => "Reset Connection",
+            Self::Other(name) => name,
+        };
+        f.write_str(name)
+    }
 }
 
 /// Error type for query formatting operations.
@@ -146,6 +559,130 @@ impl From<ParserError> for QueryError {
     }
 }
 
+/// Options controlling how a slow log is parsed.
+///
+/// Pass to [`SlowLogIter::with_options`]. The callback functions always use the
+/// defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Include the current schema in each query's fingerprint, so identical
+    /// statements run against different databases are grouped separately.
+    /// Entries without a known schema are fingerprinted as usual.
+    pub schema_in_fingerprint: bool,
+    /// Server the log was written by, which decides how vendor-specific
+    /// header lines are read.
+    pub flavour: Flavour,
+}
+
+/// Server family a slow log was written by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Flavour {
+    /// Detect the server from the startup banner, reading the log as
+    /// [`Flavour::MySql`] until a MariaDB banner is seen
+    #[default]
+    Auto,
+    /// MySQL or Percona Server; Percona fields fill [`QueryStats::percona`]
+    MySql,
+    /// MariaDB; its fields and `# explain:` blocks fill [`QueryStats::mariadb`]
+    MariaDb,
+}
+
+/// A problem found while parsing the header lines of an entry.
+///
+/// Diagnostics never stop processing. They are attached to the entry they were
+/// found in, and the affected fields keep their previous values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// A `# Time:` header could not be parsed, for example because it names
+    /// an invalid calendar date. The entry keeps the last valid timestamp.
+    InvalidTimestamp { line: usize, value: String },
+
+    /// The start time from `SET timestamp` plus the query time does not
+    /// match the end time from the entry's own `# Time:` header, allowing
+    /// one second for rounding.
+    TimeMismatch {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+impl core::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidTimestamp { line, value } => {
+                write!(f, "Invalid timestamp on line {line}: {value}")
+            }
+            Self::TimeMismatch { start, end } => {
+                write!(f, "Start time {start} does not match end time {end}")
+            }
+        }
+    }
+}
+
+/// A log entry whose SQL text could not be normalised.
+///
+/// Carries everything needed to record or report the failed entry.
+///
+/// # Fields
+///
+/// * `error` - Why the query could not be formatted
+/// * `query` - The raw SQL text of the entry
+/// * `stats` - Execution statistics and metadata for the entry
+/// * `line` - Line number (1-based) on which the entry started
+/// * `diagnostics` - Problems found in the entry's header lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryError {
+    pub error: QueryError,
+    pub query: String,
+    pub stats: QueryStats,
+    pub line: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl core::fmt::Display for EntryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Entry at line {}: {}", self.line, self.error)
+    }
+}
+
+impl core::error::Error for EntryError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Error type yielded by [`SlowLogIter`].
+///
+/// Separates failures of the underlying line source, which usually end
+/// processing, from failures of individual entries, which do not.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error<E> {
+    /// Reading from the line source failed.
+    Read(E),
+
+    /// A single entry could not be normalised. Iteration continues with the
+    /// next entry.
+    Entry(Box<EntryError>),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Read(err) => write!(f, "Failed to read log: {err}"),
+            Self::Entry(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Read(err) => Some(err),
+            Self::Entry(err) => Some(err),
+        }
+    }
+}
+
 /// Processes a MySQL slow query log from a string slice.
 ///
 /// Parses a slow query log provided as a `&str`, calling the provided callback
@@ -164,8 +701,10 @@ pub fn process_slow_log_str<Q: FnMut(Query)>(content: &str, query_callback: Q) {
 
 /// Processes slow log data from an iterator of lines.
 ///
-/// Core processing function that accepts any iterator yielding `Result<S, E>`
-/// where `S: AsRef<str>`.
+/// Accepts any iterator yielding `Result<S, E>` where `S: AsRef<str>` and
+/// drives a [`SlowLogIter`] over it. Entries whose SQL cannot be normalised are
+/// skipped; use [`SlowLogIter`] directly to receive them as [`Error::Entry`], or
+/// when you need iterator adapters or early exit.
 ///
 /// For file or buffered reader input, prefer [`process_slow_log_file`] or
 /// [`process_slow_log_reader`], which delegate to this function.
@@ -186,8 +725,8 @@ pub fn process_slow_log_str<Q: FnMut(Query)>(content: &str, query_callback: Q) {
 /// use slowlog::process_slow_log_lines;
 ///
 /// let data = "# Time: 2024-01-01T00:00:00.000000Z\n\
-///             # User@Host: user[user] @  [127.0.0.1]\n\
-///             # Query_time: 1.5  Lock_time: 0.1 Rows_sent: 10  Rows_examined: 1000\n\
+///             ## User@Host: user[user] @  [127.0.0.1]\n\
+///             ## Query_time: 1.5  Lock_time: 0.1 Rows_sent: 10  Rows_examined: 1000\n\
 ///             SELECT * FROM users WHERE id = 1;";
 ///
 /// process_slow_log_lines(
@@ -203,67 +742,12 @@ where
     S: AsRef<str>,
     Q: FnMut(Query),
 {
-    let mut current_query = String::new();
-    let mut current_stats = QueryStats {
-        user: String::new(),
-        host: String::new(),
-        time: Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap(),
-        query_time: 0.0,
-        lock_time: 0.0,
-        rows_sent: 0,
-        rows_examined: 0,
-    };
-
-    for line in lines {
-        let line = line?;
-        let line = line.as_ref();
-
-        if helpers::match_bin(line)
-            || helpers::match_set(line)
-            || helpers::match_use(line)
-            || helpers::match_tcp(line)
-        {
-            continue;
-        }
-
-        if let Some(timestamp) = helpers::parse_timestamp(line) {
-            current_stats.time = timestamp;
-            continue;
-        }
-
-        if let Some((user, host)) = helpers::parse_user_host(line) {
-            if !current_query.is_empty() {
-                match sql::format_query(&current_query) {
-                    Ok(formatted) => {
-                        let fingerprint = sql::fingerprint_query(&formatted);
-                        query_callback(Query {
-                            query: current_query.trim().to_string(),
-                            formatted,
-                            fingerprint,
-                            stats: current_stats.clone(),
-                        });
-                    }
-                    Err(e) => eprintln!("Error formatting query: {e}"),
-                }
-            }
-
-            current_stats.user = user;
-            current_stats.host = host;
-            current_query = String::new();
-            continue;
-        }
-
-        if let Some((query_time, lock_time, rows_sent, rows_examined)) =
-            helpers::parse_query_stats(line)
-        {
-            current_stats.query_time = query_time;
-            current_stats.lock_time = lock_time;
-            current_stats.rows_sent = rows_sent;
-            current_stats.rows_examined = rows_examined;
-            continue;
+    for query in SlowLogIter::new(lines) {
+        match query {
+            Ok(query) => query_callback(query),
+            Err(Error::Read(err)) => return Err(err),
+            Err(Error::Entry(_)) => {}
         }
-
-        current_query = format!("{current_query} {line}");
     }
 
     Ok(())
@@ -311,7 +795,8 @@ where
 /// type implementing `BufRead`, allowing you to process data from files, network
 /// streams, in-memory buffers, or any other buffered source.
 ///
-/// Delegates to [`process_slow_log_lines`].
+/// Delegates to [`process_slow_log_lines`]. See [`SlowLogIter::from_reader`]
+/// for a pull-based alternative.
 ///
 /// # Arguments
 ///
@@ -330,8 +815,8 @@ where
 /// use std::io::BufReader;
 ///
 /// let data = b"# Time: 2024-01-01T00:00:00.000000Z
-/// # User@Host: user[user] @  [127.0.0.1]
-/// # Query_time: 1.5  Lock_time: 0.1 Rows_sent: 10  Rows_examined: 1000
+/// ## User@Host: user[user] @  [127.0.0.1]
+/// ## Query_time: 1.5  Lock_time: 0.1 Rows_sent: 10  Rows_examined: 1000
 /// SELECT * FROM users WHERE id = 1;";
 ///
 /// let reader = BufReader::new(&data[..]);
@@ -350,6 +835,7 @@ pub fn process_slow_log_reader<R: BufRead, Q: FnMut(Query)>(
 #[cfg(all(test, feature = "readers"))]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use std::io::BufReader;
 
     #[test]
@@ -362,8 +848,6 @@ SELECT * FROM users WHERE id = 123;
 # User@Host: admin[admin] @  [127.0.0.1]
 # Query_time: 0.5  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
 SELECT * FROM products WHERE name = 'Test';
-# Time: 2024-01-01T12:02:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
 
         let reader = BufReader::new(&data[..]);
@@ -377,7 +861,8 @@ SELECT * FROM products WHERE name = 'Test';
         assert_eq!(queries.len(), 2);
 
         // First query
-        assert_eq!(queries[0].stats.user, "testuser[testuser]");
+        assert_eq!(queries[0].stats.user, "testuser");
+        assert_eq!(queries[0].stats.auth_user, "testuser");
         assert_eq!(queries[0].stats.host, "192.168.1.100");
         assert!((queries[0].stats.query_time - 2.5).abs() < 0.1);
         assert!((queries[0].stats.lock_time - 0.01).abs() < 0.1);
@@ -387,7 +872,7 @@ SELECT * FROM products WHERE name = 'Test';
         assert_eq!(queries[0].formatted, "SELECT * FROM users WHERE id = ?");
 
         // Second query
-        assert_eq!(queries[1].stats.user, "admin[admin]");
+        assert_eq!(queries[1].stats.user, "admin");
         assert_eq!(queries[1].stats.host, "127.0.0.1");
         assert!((queries[1].stats.query_time - 0.5).abs() < 0.1);
         assert!((queries[1].stats.lock_time - 0.0).abs() < 0.1);
@@ -411,8 +896,6 @@ USE testdb;
 # User@Host: user[user] @  [10.0.0.1]
 # Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
 SELECT 1;
-# Time: 2024-01-01T12:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
 
         let reader = BufReader::new(&data[..]);
@@ -456,8 +939,6 @@ SELECT *
 FROM users
 WHERE age > 18
 AND status = 'active';
-# Time: 2024-01-01T12:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
 
         let reader = BufReader::new(&data[..]);
@@ -486,16 +967,43 @@ AND status = 'active';
 SELECT * FROM";
 
         let reader = BufReader::new(&data[..]);
-        let mut count = 0;
+        let mut queries = Vec::new();
 
-        // Should not panic on invalid SQL, just skip it
-        process_slow_log_reader(reader, |_| {
-            count += 1;
+        // Should not panic on invalid SQL, just fall back to the lexical normaliser
+        process_slow_log_reader(reader, |query| {
+            queries.push(query);
         })
         .expect("Failed to process slow log");
 
-        // Invalid SQL should be skipped (error printed to stderr)
-        assert_eq!(count, 0);
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].normaliser, Normaliser::Lexical);
+        assert_eq!(queries[0].formatted, "select * from");
+    }
+
+    #[test]
+    fn test_process_slow_log_str_lexical_fallback() {
+        let data = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.5  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+LOAD DATA INFILE '/tmp/orders-1.csv' INTO TABLE orders;
+# Time: 2024-01-01T12:01:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.5  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+LOAD DATA INFILE '/tmp/orders-2.csv' INTO TABLE orders;
+# Time: 2024-01-01T12:02:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 0.5  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
+SELECT 1;
+";
+        let mut queries = Vec::new();
+        process_slow_log_str(data, |query| {
+            queries.push(query);
+        });
+        assert_eq!(queries.len(), 3);
+        assert_eq!(queries[0].normaliser, Normaliser::Lexical);
+        assert_eq!(queries[0].formatted, "load data infile ? into table orders");
+        assert_eq!(queries[0].fingerprint, queries[1].fingerprint);
+        assert_eq!(queries[2].normaliser, Normaliser::Parser);
     }
 
     #[test]
@@ -504,16 +1012,12 @@ SELECT * FROM";
 # User@Host: user[user] @  [127.0.0.1]
 # Query_time: 1.0  Lock_time: 0.0 Rows_sent: 10  Rows_examined: 100
 SELECT * FROM users WHERE id = 123;
-# Time: 2024-01-01T12:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
 
         let data2 = b"# Time: 2024-01-01T13:00:00.000000Z
 # User@Host: admin[admin] @  [10.0.0.1]
 # Query_time: 2.0  Lock_time: 0.5 Rows_sent: 20  Rows_examined: 200
 SELECT * FROM users WHERE id = 456;
-# Time: 2024-01-01T13:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
 
         let reader1 = BufReader::new(&data1[..]);
@@ -548,8 +1052,6 @@ SELECT * FROM users WHERE id = 456;
 # User@Host: user[user] @  [127.0.0.1]
 # Query_time: 1.0  Lock_time: 0.0 Rows_sent: 5  Rows_examined: 50
 INSERT INTO logs (message) VALUES ('test');
-# Time: 2024-01-01T12:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 "
         )
         .expect("Failed to process slow log");
@@ -591,16 +1093,16 @@ INSERT INTO logs (message) VALUES ('test');
 # User@Host: testuser[testuser] @  [192.168.1.100]
 # Query_time: 2.5  Lock_time: 0.01 Rows_sent: 100  Rows_examined: 5000
 SELECT * FROM users WHERE id = 123;
-# Time: 2024-01-01T12:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
         let mut queries = Vec::new();
         process_slow_log_str(data, |query| {
             queries.push(query);
         });
         assert_eq!(queries.len(), 1);
-        assert_eq!(queries[0].stats.user, "testuser[testuser]");
+        assert_eq!(queries[0].stats.user, "testuser");
+        assert_eq!(queries[0].stats.auth_user, "testuser");
         assert_eq!(queries[0].stats.host, "192.168.1.100");
+        assert_eq!(queries[0].stats.thread_id, None);
         assert!((queries[0].stats.query_time - 2.5).abs() < 0.1);
         assert_eq!(queries[0].formatted, "SELECT * FROM users WHERE id = ?");
     }
@@ -614,8 +1116,6 @@ USE testdb;
 # User@Host: user[user] @  [10.0.0.1]
 # Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
 SELECT 1;
-# Time: 2024-01-01T12:01:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
         let mut queries = Vec::new();
         process_slow_log_str(data, |query| {
@@ -631,13 +1131,11 @@ SELECT 1;
         let data = "# Time: 2024-01-01T12:00:00.000000Z
 # User@Host: user[user] @  [127.0.0.1]
 # Query_time: 0.5  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
-SELECT * FROM
+SELECT * FROM t WHERE name = 'unterminated
 # Time: 2024-01-01T12:01:00.000000Z
 # User@Host: user2[user2] @  [127.0.0.1]
 # Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
 SELECT 1;
-# Time: 2024-01-01T12:02:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
         let mut count = 0;
         process_slow_log_str(data, |_| {
@@ -653,13 +1151,11 @@ SELECT 1;
         let data = b"# Time: 2024-01-01T12:00:00.000000Z
 # User@Host: user[user] @  [127.0.0.1]
 # Query_time: 0.5  Lock_time: 0.0 Rows_sent: 0  Rows_examined: 0
-SELECT * FROM
+SELECT * FROM t WHERE name = 'unterminated
 # Time: 2024-01-01T12:01:00.000000Z
 # User@Host: user2[user2] @  [127.0.0.1]
 # Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
 SELECT 1;
-# Time: 2024-01-01T12:02:00.000000Z
-# User@Host: final[final] @  [127.0.0.1]
 ";
         let reader = BufReader::new(&data[..]);
         let mut count = 0;
@@ -670,4 +1166,176 @@ SELECT 1;
         // First query (invalid SQL) is skipped, second succeeds
         assert_eq!(count, 1);
     }
+
+    #[test]
+    fn test_process_slow_log_str_emits_final_entry() {
+        let data = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: 2024-01-01T12:01:00.000000Z
+# User@Host: user2[user2] @  [127.0.0.1]
+# Query_time: 2.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT * FROM users WHERE id = 1;";
+        let mut queries = Vec::new();
+        process_slow_log_str(data, |query| {
+            queries.push(query);
+        });
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[1].stats.user, "user2");
+        assert_eq!(queries[1].formatted, "SELECT * FROM users WHERE id = ?");
+    }
+
+    #[test]
+    fn test_process_slow_log_str_time_boundary() {
+        // The second entry has no User@Host line, as in a truncated or rotated file
+        let data = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: 2024-01-01T12:01:00.000000Z
+# Query_time: 2.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 2;
+";
+        let mut queries = Vec::new();
+        process_slow_log_str(data, |query| {
+            queries.push(query);
+        });
+        assert_eq!(queries.len(), 2);
+        assert_eq!(
+            queries[0].stats.time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+        );
+        assert_eq!(
+            queries[1].stats.time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 1, 0).unwrap()
+        );
+        assert!((queries[1].stats.query_time - 2.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_entry_error_display() {
+        let err = EntryError {
+            error: QueryError::InvalidQuery,
+            query: ";".to_string(),
+            stats: QueryStats::default(),
+            line: 42,
+            diagnostics: Vec::new(),
+        };
+        assert_eq!(
+            format!("{err}"),
+            "Entry at line 42: No valid SQL statement found"
+        );
+        assert_eq!(
+            format!("{}", Error::<io::Error>::Entry(Box::new(err))),
+            format!("Entry at line 42: No valid SQL statement found")
+        );
+    }
+
+    #[test]
+    fn test_error_display_read() {
+        let err = Error::Read(io::Error::other("disk on fire"));
+        assert_eq!(format!("{err}"), "Failed to read log: disk on fire");
+    }
+
+    #[test]
+    fn test_process_slow_log_str_legacy_time_carried_forward() {
+        // Older servers only write `# Time:` when the second changes
+        let data = "# Time: 240101 12:00:00
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 2;
+# Time: 240101  12:00:01
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 3;
+";
+        let mut times = Vec::new();
+        process_slow_log_str(data, |query| {
+            times.push(query.stats.time);
+        });
+        let first = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 1).unwrap();
+        assert_eq!(times, [first, first, second]);
+    }
+
+    #[test]
+    fn test_process_slow_log_str_invalid_timestamp() {
+        let data = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: 2024-13-45T99:00:00Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 2;
+# Time: 2024-01-01T12:02:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 3;
+";
+        let mut queries = Vec::new();
+        process_slow_log_str(data, |query| {
+            queries.push(query);
+        });
+        assert_eq!(queries.len(), 3);
+        assert!(queries[0].diagnostics.is_empty());
+        assert_eq!(queries[1].query, "SELECT 2;");
+        assert_eq!(
+            queries[1].diagnostics,
+            [Diagnostic::InvalidTimestamp {
+                line: 5,
+                value: "2024-13-45T99:00:00Z".to_string(),
+            }]
+        );
+        assert_eq!(queries[1].stats.time, queries[0].stats.time);
+        assert!(queries[2].diagnostics.is_empty());
+        assert_eq!(
+            queries[2].stats.time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 2, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let diagnostic = Diagnostic::InvalidTimestamp {
+            line: 7,
+            value: "garbage".to_string(),
+        };
+        assert_eq!(
+            format!("{diagnostic}"),
+            "Invalid timestamp on line 7: garbage"
+        );
+
+        let diagnostic = Diagnostic::TimeMismatch {
+            start: Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 5).unwrap(),
+        };
+        assert_eq!(
+            format!("{diagnostic}"),
+            "Start time 2024-01-01 12:00:00 UTC does not match end time 2024-01-01 12:00:05 UTC"
+        );
+    }
+
+    #[test]
+    fn test_process_slow_log_str_thread_id() {
+        let data = "# Time: 2024-01-01T12:00:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]  Id: 541085
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 1;
+# Time: 2024-01-01T12:01:00.000000Z
+# User@Host: user[user] @  [127.0.0.1]
+# Query_time: 1.0  Lock_time: 0.0 Rows_sent: 1  Rows_examined: 1
+SELECT 2;
+";
+        let mut thread_ids = Vec::new();
+        process_slow_log_str(data, |query| {
+            thread_ids.push(query.stats.thread_id);
+        });
+        // The id is per entry and does not carry over to entries without one
+        assert_eq!(thread_ids, [Some(541_085), None]);
+    }
 }
@@ -1,8 +1,11 @@
 use super::QueryError;
 use sha1::{Digest, Sha1};
 use sqlparser::ast::{
-    Expr, Function, FunctionArg, FunctionArgExpr, FunctionArguments, OnInsert, SelectItem, SetExpr,
-    Statement, Value, ValueWithSpan,
+    Expr, FromTable, Function, FunctionArg, FunctionArgExpr, FunctionArguments, GroupByExpr,
+    JoinConstraint, JoinOperator, JsonTableColumn, JsonTableColumnErrorHandling, LimitClause,
+    NamedWindowExpr, OnInsert, OrderByExpr, OrderByKind, Query, Select, SelectItem, SetExpr,
+    Statement, TableFactor, TableWithJoins, UpdateTableFromKind, Value, ValueWithSpan,
+    WindowFrameBound, WindowSpec, WindowType,
 };
 use sqlparser::dialect::MySqlDialect;
 use sqlparser::parser::Parser;
@@ -10,33 +13,17 @@ use sqlparser::tokenizer::Span;
 
 fn replace_values_with_placeholders(statement: &mut Statement) {
     match statement {
-        Statement::Query(query) => {
-            if let SetExpr::Select(select) = query.body.as_mut() {
-                if let Some(ref mut selection) = select.selection {
-                    replace_values_in_expr(selection);
-                }
-                for item in &mut select.projection {
-                    replace_values_in_select_item(item);
-                }
-            }
-            if let Some(ref mut limit_clause) = query.limit_clause {
-                match limit_clause {
-                    sqlparser::ast::LimitClause::LimitOffset { limit, offset, .. } => {
-                        if let Some(limit) = limit {
-                            replace_values_in_expr(limit);
-                        }
-                        if let Some(offset) = offset {
-                            replace_values_in_expr(&mut offset.value);
-                        }
-                    }
-                    sqlparser::ast::LimitClause::OffsetCommaLimit { offset, limit } => {
-                        replace_values_in_expr(offset);
-                        replace_values_in_expr(limit);
-                    }
+        Statement::Query(query) => replace_values_in_query(query),
+        Statement::Update(update) => {
+            replace_values_in_table_with_joins(&mut update.table);
+            if let Some(
+                UpdateTableFromKind::BeforeSet(tables) | UpdateTableFromKind::AfterSet(tables),
+            ) = &mut update.from
+            {
+                for table in tables {
+                    replace_values_in_table_with_joins(table);
                 }
             }
-        }
-        Statement::Update(update) => {
             for assignment in &mut update.assignments {
                 replace_values_in_expr(&mut assignment.value);
             }
@@ -46,27 +33,7 @@ fn replace_values_with_placeholders(statement: &mut Statement) {
         }
         Statement::Insert(insert) => {
             if let Some(source) = insert.source.as_mut() {
-                match source.body.as_mut() {
-                    SetExpr::Values(values) => {
-                        for value in &mut values.rows {
-                            for expr in value {
-                                replace_values_in_expr(expr);
-                            }
-                        }
-                    }
-                    SetExpr::Select(select) => {
-                        if let Some(ref mut selection) = select.selection {
-                            replace_values_in_expr(selection);
-                        }
-                        for item in &mut select.projection {
-                            replace_values_in_select_item(item);
-                        }
-                        if let Some(ref mut having) = select.having {
-                            replace_values_in_expr(having);
-                        }
-                    }
-                    _ => {}
-                }
+                replace_values_in_query(source);
             }
             if let Some(OnInsert::DuplicateKeyUpdate(assignments)) = &mut insert.on {
                 for assignment in assignments {
@@ -75,14 +42,228 @@ fn replace_values_with_placeholders(statement: &mut Statement) {
             }
         }
         Statement::Delete(delete) => {
+            let (FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables)) =
+                &mut delete.from;
+            for table in tables.iter_mut().chain(delete.using.iter_mut().flatten()) {
+                replace_values_in_table_with_joins(table);
+            }
             if let Some(ref mut selection) = delete.selection {
                 replace_values_in_expr(selection);
             }
         }
+        Statement::Call(func) => replace_values_in_function(func),
+        _ => {}
+    }
+}
+
+fn replace_values_in_query(query: &mut Query) {
+    if let Some(ref mut with) = query.with {
+        for cte in &mut with.cte_tables {
+            replace_values_in_query(&mut cte.query);
+        }
+    }
+    replace_values_in_set_expr(&mut query.body);
+    if let Some(ref mut order_by) = query.order_by {
+        if let OrderByKind::Expressions(exprs) = &mut order_by.kind {
+            for order_by_expr in exprs {
+                replace_values_in_order_by_expr(order_by_expr);
+            }
+        }
+    }
+    if let Some(ref mut limit_clause) = query.limit_clause {
+        match limit_clause {
+            LimitClause::LimitOffset { limit, offset, .. } => {
+                if let Some(limit) = limit {
+                    replace_values_in_expr(limit);
+                }
+                if let Some(offset) = offset {
+                    replace_values_in_expr(&mut offset.value);
+                }
+            }
+            LimitClause::OffsetCommaLimit { offset, limit } => {
+                replace_values_in_expr(offset);
+                replace_values_in_expr(limit);
+            }
+        }
+    }
+}
+
+/// Walks a query body, recursing into both sides of `UNION`, `INTERSECT` and
+/// `EXCEPT` and into parenthesised queries.
+fn replace_values_in_set_expr(set_expr: &mut SetExpr) {
+    match set_expr {
+        SetExpr::Select(select) => replace_values_in_select(select),
+        SetExpr::Query(query) => replace_values_in_query(query),
+        SetExpr::SetOperation { left, right, .. } => {
+            replace_values_in_set_expr(left);
+            replace_values_in_set_expr(right);
+        }
+        SetExpr::Values(values) => {
+            for row in &mut values.rows {
+                for expr in row {
+                    replace_values_in_expr(expr);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn replace_values_in_select(select: &mut Select) {
+    for table in &mut select.from {
+        replace_values_in_table_with_joins(table);
+    }
+    if let Some(ref mut selection) = select.selection {
+        replace_values_in_expr(selection);
+    }
+    for item in &mut select.projection {
+        replace_values_in_select_item(item);
+    }
+    if let GroupByExpr::Expressions(exprs, _) = &mut select.group_by {
+        for expr in exprs {
+            replace_values_in_positional_expr(expr);
+        }
+    }
+    if let Some(ref mut having) = select.having {
+        replace_values_in_expr(having);
+    }
+    for window in &mut select.named_window {
+        if let NamedWindowExpr::WindowSpec(spec) = &mut window.1 {
+            replace_values_in_window_spec(spec);
+        }
+    }
+    if let Some(ref mut qualify) = select.qualify {
+        replace_values_in_expr(qualify);
+    }
+}
+
+fn replace_values_in_order_by_expr(order_by_expr: &mut OrderByExpr) {
+    replace_values_in_positional_expr(&mut order_by_expr.expr);
+}
+
+/// Walks a `GROUP BY` or `ORDER BY` item. A bare number there refers to a
+/// column by position, so it is part of the query's shape and kept as-is.
+fn replace_values_in_positional_expr(expr: &mut Expr) {
+    if !matches!(
+        expr,
+        Expr::Value(ValueWithSpan {
+            value: Value::Number(..),
+            ..
+        })
+    ) {
+        replace_values_in_expr(expr);
+    }
+}
+
+/// Walks a window definition, including frame bounds such as
+/// `ROWS BETWEEN 3 PRECEDING AND CURRENT ROW`.
+fn replace_values_in_window_spec(spec: &mut WindowSpec) {
+    for expr in &mut spec.partition_by {
+        replace_values_in_expr(expr);
+    }
+    for order_by_expr in &mut spec.order_by {
+        replace_values_in_order_by_expr(order_by_expr);
+    }
+    if let Some(ref mut window_frame) = spec.window_frame {
+        let bounds =
+            std::iter::once(&mut window_frame.start_bound).chain(window_frame.end_bound.as_mut());
+        for bound in bounds {
+            if let WindowFrameBound::Preceding(Some(expr))
+            | WindowFrameBound::Following(Some(expr)) = bound
+            {
+                replace_values_in_expr(expr);
+            }
+        }
+    }
+}
+
+/// Walks a `FROM` item and its joins, including `ON` conditions, derived
+/// tables and table function arguments.
+fn replace_values_in_table_with_joins(table: &mut TableWithJoins) {
+    replace_values_in_table_factor(&mut table.relation);
+    for join in &mut table.joins {
+        replace_values_in_table_factor(&mut join.relation);
+        let constraint = match &mut join.join_operator {
+            JoinOperator::Join(constraint)
+            | JoinOperator::Inner(constraint)
+            | JoinOperator::Left(constraint)
+            | JoinOperator::LeftOuter(constraint)
+            | JoinOperator::Right(constraint)
+            | JoinOperator::RightOuter(constraint)
+            | JoinOperator::FullOuter(constraint)
+            | JoinOperator::CrossJoin(constraint)
+            | JoinOperator::Semi(constraint)
+            | JoinOperator::LeftSemi(constraint)
+            | JoinOperator::RightSemi(constraint)
+            | JoinOperator::Anti(constraint)
+            | JoinOperator::LeftAnti(constraint)
+            | JoinOperator::RightAnti(constraint)
+            | JoinOperator::StraightJoin(constraint) => constraint,
+            JoinOperator::AsOf {
+                match_condition,
+                constraint,
+            } => {
+                replace_values_in_expr(match_condition);
+                constraint
+            }
+            JoinOperator::CrossApply | JoinOperator::OuterApply => continue,
+        };
+        if let JoinConstraint::On(expr) = constraint {
+            replace_values_in_expr(expr);
+        }
+    }
+}
+
+fn replace_values_in_table_factor(table_factor: &mut TableFactor) {
+    match table_factor {
+        TableFactor::Table {
+            args: Some(args), ..
+        } => {
+            for arg in &mut args.args {
+                replace_values_in_function_arg(arg);
+            }
+        }
+        TableFactor::Function { args, .. } => {
+            for arg in args {
+                replace_values_in_function_arg(arg);
+            }
+        }
+        TableFactor::Derived { subquery, .. } => replace_values_in_query(subquery),
+        TableFactor::TableFunction { expr, .. } => replace_values_in_expr(expr),
+        TableFactor::JsonTable {
+            json_expr, columns, ..
+        } => {
+            replace_values_in_expr(json_expr);
+            replace_values_in_json_table_columns(columns);
+        }
+        TableFactor::NestedJoin {
+            table_with_joins, ..
+        } => replace_values_in_table_with_joins(table_with_joins),
         _ => {}
     }
 }
 
+/// Replaces `DEFAULT` values of `JSON_TABLE` columns. Column paths describe
+/// the shape of the document and are kept.
+fn replace_values_in_json_table_columns(columns: &mut [JsonTableColumn]) {
+    for column in columns {
+        match column {
+            JsonTableColumn::Named(column) => {
+                let handlers = [&mut column.on_empty, &mut column.on_error];
+                for handler in handlers.into_iter().flatten() {
+                    if let JsonTableColumnErrorHandling::Default(value) = handler {
+                        *value = Value::Placeholder("?".to_string());
+                    }
+                }
+            }
+            JsonTableColumn::Nested(nested) => {
+                replace_values_in_json_table_columns(&mut nested.columns);
+            }
+            JsonTableColumn::ForOrdinality(_) => {}
+        }
+    }
+}
+
 fn replace_values_in_select_item(select_item: &mut SelectItem) {
     match select_item {
         SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
@@ -95,23 +276,33 @@ fn replace_values_in_select_item(select_item: &mut SelectItem) {
 fn replace_values_in_function(func: &mut Function) {
     if let FunctionArguments::List(list) = &mut func.args {
         for arg in &mut list.args {
-            match arg {
-                FunctionArg::Unnamed(expr) => {
-                    if let FunctionArgExpr::Expr(expr) = expr {
-                        replace_values_in_expr(expr);
-                    }
-                }
-                FunctionArg::Named { arg, .. } => {
-                    if let FunctionArgExpr::Expr(expr) = arg {
-                        replace_values_in_expr(expr);
-                    }
-                }
-                FunctionArg::ExprNamed { name, arg, .. } => {
-                    replace_values_in_expr(name);
-                    if let FunctionArgExpr::Expr(expr) = arg {
-                        replace_values_in_expr(expr);
-                    }
-                }
+            replace_values_in_function_arg(arg);
+        }
+    }
+    if let Some(ref mut filter) = func.filter {
+        replace_values_in_expr(filter);
+    }
+    if let Some(WindowType::WindowSpec(spec)) = &mut func.over {
+        replace_values_in_window_spec(spec);
+    }
+}
+
+fn replace_values_in_function_arg(arg: &mut FunctionArg) {
+    match arg {
+        FunctionArg::Unnamed(expr) => {
+            if let FunctionArgExpr::Expr(expr) = expr {
+                replace_values_in_expr(expr);
+            }
+        }
+        FunctionArg::Named { arg, .. } => {
+            if let FunctionArgExpr::Expr(expr) = arg {
+                replace_values_in_expr(expr);
+            }
+        }
+        FunctionArg::ExprNamed { name, arg, .. } => {
+            replace_values_in_expr(name);
+            if let FunctionArgExpr::Expr(expr) = arg {
+                replace_values_in_expr(expr);
             }
         }
     }
@@ -161,7 +352,9 @@ fn replace_values_in_expr(expr: &mut Expr) {
         | Expr::UnaryOp { expr, .. } => {
             replace_values_in_expr(expr);
         }
-        Expr::BinaryOp { left, right, .. } => {
+        Expr::BinaryOp { left, right, .. }
+        | Expr::AnyOp { left, right, .. }
+        | Expr::AllOp { left, right, .. } => {
             replace_values_in_expr(left);
             replace_values_in_expr(right);
         }
@@ -190,26 +383,13 @@ fn replace_values_in_expr(expr: &mut Expr) {
             replace_values_in_function(func);
         }
         Expr::Subquery(query) => {
-            if let SetExpr::Select(select) = query.body.as_mut() {
-                if let Some(ref mut selection) = select.selection {
-                    replace_values_in_expr(selection);
-                } else {
-                    for item in &mut select.projection {
-                        replace_values_in_select_item(item);
-                    }
-                }
-            }
+            replace_values_in_query(query);
+        }
+        Expr::Exists { subquery, .. } => {
+            replace_values_in_query(subquery);
         }
         Expr::InSubquery { expr, subquery, .. } => {
-            if let SetExpr::Select(select) = subquery.body.as_mut() {
-                if let Some(ref mut selection) = select.selection {
-                    replace_values_in_expr(selection);
-                } else {
-                    for item in &mut select.projection {
-                        replace_values_in_select_item(item);
-                    }
-                }
-            }
+            replace_values_in_query(subquery);
             replace_values_in_expr(expr);
         }
         Expr::GroupingSets(grouping_sets) => {
@@ -223,21 +403,30 @@ fn replace_values_in_expr(expr: &mut Expr) {
     }
 }
 
-/// Formats a SQL query by replacing all literal values with placeholders.
+/// Formats each statement of a SQL query by replacing all literal values with
+/// placeholders.
 ///
-/// This function parses the input SQL query, replaces all literal values
-/// (strings, numbers, etc.) with `?` placeholders, and returns the Normalised
-/// query string. This is useful for query fingerprinting and grouping similar
+/// This function parses the input SQL, which may hold several statements
+/// separated by `;`, replaces all literal values (strings, numbers, etc.) with
+/// `?` placeholders, and returns the Normalised text of every statement in
+/// order. This is useful for query fingerprinting and grouping similar
 /// queries together.
 ///
 /// Returns `QueryError::ParseError` if the SQL cannot be parsed.
 /// Returns `QueryError::InvalidQuery` if no SQL statement is found.
-pub(crate) fn format_query(input: &str) -> Result<String, QueryError> {
+pub(crate) fn format_statements(input: &str) -> Result<Vec<String>, QueryError> {
     let ast = Parser::parse_sql(&MySqlDialect {}, input)?;
-    let mut query = ast.into_iter().next().ok_or(QueryError::InvalidQuery)?;
+    if ast.is_empty() {
+        return Err(QueryError::InvalidQuery);
+    }
 
-    replace_values_with_placeholders(&mut query);
-    Ok(query.to_string())
+    Ok(ast
+        .into_iter()
+        .map(|mut statement| {
+            replace_values_with_placeholders(&mut statement);
+            statement.to_string()
+        })
+        .collect())
 }
 
 /// Calculates SHA1 hash fingerprint of a query
@@ -253,6 +442,33 @@ pub(crate) fn fingerprint_query(query: &str) -> String {
 mod tests {
     use super::*;
 
+    fn format_query(input: &str) -> Result<String, QueryError> {
+        format_statements(input).map(|statements| statements.join("; "))
+    }
+
+    #[test]
+    fn test_multiple_statements() {
+        let input = "BEGIN; UPDATE accounts SET balance = balance - 10 WHERE id = 1; COMMIT;";
+        let expected = vec![
+            "BEGIN",
+            "UPDATE accounts SET balance = balance - ? WHERE id = ?",
+            "COMMIT",
+        ];
+        assert_eq!(format_statements(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_call_statements() {
+        let input = "CALL p(1, 'a'); CALL p(2, 'b')";
+        let statements = format_statements(input).unwrap();
+        assert_eq!(statements, vec!["CALL p(?, ?)", "CALL p(?, ?)"]);
+    }
+
+    #[test]
+    fn test_empty_statements() {
+        assert_eq!(format_statements(" ; "), Err(QueryError::InvalidQuery));
+    }
+
     #[test]
     fn test_select_with_operators() {
         let input = "select * from tablename where id = 1 and name like 'test' and age > 10 and age < 20 and age != 30 and age <> 40";
@@ -450,4 +666,164 @@ mod tests {
         let expected = "SELECT (SELECT COUNT(*) FROM other), name FROM tablename WHERE id = ?";
         assert_eq!(format_query(input).unwrap(), expected);
     }
+
+    #[test]
+    fn test_union() {
+        let input = "SELECT id FROM users WHERE id = 1 UNION SELECT id FROM admins WHERE id = 2";
+        let expected = "SELECT id FROM users WHERE id = ? UNION SELECT id FROM admins WHERE id = ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_operations_nested() {
+        let input = "(SELECT a FROM t WHERE a > 1 LIMIT 5) UNION ALL (SELECT a FROM u WHERE a < 9 \
+                     INTERSECT SELECT a FROM v WHERE a = 3) EXCEPT SELECT 7 ORDER BY a LIMIT 10";
+        let expected =
+            "(SELECT a FROM t WHERE a > ? LIMIT ?) UNION ALL (SELECT a FROM u WHERE a < ? \
+                        INTERSECT SELECT a FROM v WHERE a = ?) EXCEPT SELECT ? ORDER BY a LIMIT ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_in_subquery_union() {
+        let input = "SELECT * FROM t WHERE id IN (SELECT id FROM u WHERE x = 1 UNION SELECT 2)";
+        let expected = "SELECT * FROM t WHERE id IN (SELECT id FROM u WHERE x = ? UNION SELECT ?)";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_cte() {
+        let input = "WITH recent AS (SELECT id FROM orders WHERE created_at > '2024-01-01') \
+                     SELECT COUNT(*) FROM recent WHERE id > 100";
+        let expected = "WITH recent AS (SELECT id FROM orders WHERE created_at > ?) \
+                        SELECT COUNT(*) FROM recent WHERE id > ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_recursive_cte() {
+        let input = "WITH RECURSIVE days (d) AS (SELECT CAST('2024-01-01' AS DATE) UNION ALL \
+                     SELECT d + INTERVAL 1 DAY FROM days WHERE d < '2024-01-31') SELECT d FROM days";
+        let expected = "WITH RECURSIVE days (d) AS (SELECT CAST(? AS DATE) UNION ALL \
+                        SELECT d + INTERVAL ? DAY FROM days WHERE d < ?) SELECT d FROM days";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_join_on() {
+        let input =
+            "SELECT o.id FROM orders o JOIN order_types t ON t.id = o.type_id AND t.code = 'x' \
+                     LEFT JOIN users u USING (user_id) WHERE o.id > 10";
+        let expected =
+            "SELECT o.id FROM orders o JOIN order_types t ON t.id = o.type_id AND t.code = ? \
+                        LEFT JOIN users u USING(user_id) WHERE o.id > ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_derived_table() {
+        let input =
+            "SELECT d.total FROM (SELECT SUM(amount) AS total FROM payments WHERE status = 5) d";
+        let expected =
+            "SELECT d.total FROM (SELECT SUM(amount) AS total FROM payments WHERE status = ?) d";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_lateral_derived_table() {
+        let input = "SELECT u.id, l.total FROM users u, LATERAL (SELECT COUNT(*) AS total \
+                     FROM orders o WHERE o.user_id = u.id AND o.amount > 100) AS l";
+        let expected = "SELECT u.id, l.total FROM users u, LATERAL (SELECT COUNT(*) AS total \
+                        FROM orders o WHERE o.user_id = u.id AND o.amount > ?) AS l";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_json_table() {
+        let input =
+            "SELECT jt.a FROM JSON_TABLE('[{\"a\": 1}]', '$[*]' COLUMNS(a INT PATH '$.a')) AS jt";
+        // The JSON path is part of the query's shape and is kept
+        let expected = "SELECT jt.a FROM JSON_TABLE(?, '$[*]' COLUMNS(a INT PATH '$.a')) AS jt";
+        assert_eq!(format_query(input).unwrap(), expected);
+
+        let input = "SELECT jt.a FROM JSON_TABLE(doc, '$[*]' COLUMNS(\
+                     a INT PATH '$.a' DEFAULT '5' ON EMPTY DEFAULT '0' ON ERROR, \
+                     NESTED PATH '$.b[*]' COLUMNS(b VARCHAR(10) PATH '$' DEFAULT 'x' ON EMPTY))) AS jt";
+        let expected = "SELECT jt.a FROM JSON_TABLE(doc, '$[*]' COLUMNS(\
+                        a INT PATH '$.a' DEFAULT ? ON EMPTY DEFAULT ? ON ERROR, \
+                        NESTED PATH '$.b[*]' COLUMNS (b VARCHAR(10) PATH '$' DEFAULT ? ON EMPTY))) AS jt";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_update_and_delete_joins() {
+        let input = "UPDATE orders o JOIN users u ON u.id = o.user_id AND u.tier = 'gold' SET o.discount = 5";
+        let expected =
+            "UPDATE orders o JOIN users u ON u.id = o.user_id AND u.tier = ? SET o.discount = ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+
+        let input = "DELETE o FROM orders o JOIN users u ON u.id = o.user_id AND u.banned = 1";
+        let expected = "DELETE o FROM orders o JOIN users u ON u.id = o.user_id AND u.banned = ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_group_by_having() {
+        let input = "SELECT user_id, SUM(amount) AS total FROM payments \
+                     GROUP BY user_id, IF(amount > 100, 'big', 'small') HAVING total < 10";
+        let expected = "SELECT user_id, SUM(amount) AS total FROM payments \
+                        GROUP BY user_id, IF(amount > ?, ?, ?) HAVING total < ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_order_by() {
+        let input = "SELECT * FROM users ORDER BY FIELD(id, 3, 1, 2), 2 DESC LIMIT 5";
+        let expected = "SELECT * FROM users ORDER BY FIELD(id, ?, ?, ?), 2 DESC LIMIT ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_window() {
+        let input = "SELECT id, SUM(amount) OVER (PARTITION BY user_id ORDER BY id \
+                     ROWS BETWEEN 3 PRECEDING AND 1 FOLLOWING) FROM payments";
+        let expected = "SELECT id, SUM(amount) OVER (PARTITION BY user_id ORDER BY id \
+                        ROWS BETWEEN ? PRECEDING AND ? FOLLOWING) FROM payments";
+        assert_eq!(format_query(input).unwrap(), expected);
+
+        let input = "SELECT id, AVG(amount) OVER w FROM payments \
+                     WINDOW w AS (PARTITION BY IF(amount > 5, 1, 0) ROWS 2 PRECEDING)";
+        let expected = "SELECT id, AVG(amount) OVER w FROM payments \
+                        WINDOW w AS (PARTITION BY IF(amount > ?, ?, ?) ROWS ? PRECEDING)";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_insert_select_clauses() {
+        let input = "INSERT INTO totals SELECT user_id, SUM(amount) FROM payments p \
+                     JOIN users u ON u.id = p.user_id AND u.active = 1 \
+                     GROUP BY user_id HAVING SUM(amount) > 100 ORDER BY user_id LIMIT 10";
+        let expected = "INSERT INTO totals SELECT user_id, SUM(amount) FROM payments p \
+                        JOIN users u ON u.id = p.user_id AND u.active = ? \
+                        GROUP BY user_id HAVING SUM(amount) > ? ORDER BY user_id LIMIT ?";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_exists_subquery() {
+        let input = "SELECT * FROM t WHERE EXISTS (SELECT 1 FROM u WHERE u.x = 5) \
+                     AND NOT EXISTS (SELECT 1 FROM v WHERE v.y = 'a')";
+        let expected = "SELECT * FROM t WHERE EXISTS (SELECT ? FROM u WHERE u.x = ?) \
+                        AND NOT EXISTS (SELECT ? FROM v WHERE v.y = ?)";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_any_all_subquery() {
+        let input = "SELECT * FROM t WHERE a > ANY (SELECT b FROM u WHERE u.x = 5) \
+                     AND a < ALL (SELECT c FROM v WHERE v.y = 'a')";
+        let expected = "SELECT * FROM t WHERE a > ANY(SELECT b FROM u WHERE u.x = ?) \
+                        AND a < ALL(SELECT c FROM v WHERE v.y = ?)";
+        assert_eq!(format_query(input).unwrap(), expected);
+    }
 }